@@ -0,0 +1,83 @@
+use crate::space::{wrap, Space, MAX_COORD, MIN_COORD};
+
+const SIDE: usize = (MAX_COORD - MIN_COORD + 1) as usize;
+
+fn index(x: i64, y: i64) -> usize {
+    (wrap(y) - MIN_COORD) as usize * SIDE + (wrap(x) - MIN_COORD) as usize
+}
+
+// Per-cell history of the generations since recording started
+#[derive(Clone, Debug)]
+pub struct Activity {
+    // Generations observed
+    generations: u64,
+    // Number of generations each cell changed state in
+    changes: Vec<u32>,
+    // Step each cell last changed state at
+    last_change: Vec<Option<u64>>,
+    // Whether each cell was ever non-zero
+    visited: Vec<bool>,
+}
+
+impl Activity {
+    pub fn new(space: &Space) -> Self {
+        let mut activity = Activity {
+            generations: 0,
+            changes: vec![0; SIDE * SIDE],
+            last_change: vec![None; SIDE * SIDE],
+            visited: vec![false; SIDE * SIDE],
+        };
+        for y in MIN_COORD..=MAX_COORD {
+            for x in MIN_COORD..=MAX_COORD {
+                if space.get(x, y).0 != 0 {
+                    activity.visited[index(x, y)] = true;
+                }
+            }
+        }
+        activity
+    }
+
+    // Compare the current cells with the next ones, before the step is finalized
+    pub fn observe_step(&mut self, space: &Space) {
+        self.generations += 1;
+        let step = space.current_step() + 1;
+        for y in MIN_COORD..=MAX_COORD {
+            for x in MIN_COORD..=MAX_COORD {
+                let new_cell = space.get_new(x, y);
+                if space.get(x, y) == new_cell {
+                    continue;
+                }
+                let idx = index(x, y);
+                self.changes[idx] += 1;
+                self.last_change[idx] = Some(step);
+                if new_cell.0 != 0 {
+                    self.visited[idx] = true;
+                }
+            }
+        }
+    }
+
+    // A cell was edited by hand
+    pub fn observe_edit(&mut self, x: i64, y: i64, non_zero: bool) {
+        if non_zero {
+            self.visited[index(x, y)] = true;
+        }
+    }
+
+    // Share of the observed generations the cell changed state in
+    pub fn change_frequency(&self, x: i64, y: i64) -> f32 {
+        if self.generations == 0 {
+            return 0.0;
+        }
+        self.changes[index(x, y)] as f32 / self.generations as f32
+    }
+
+    // Step the cell last changed state at, None if it never did
+    pub fn last_change(&self, x: i64, y: i64) -> Option<u64> {
+        self.last_change[index(x, y)]
+    }
+
+    pub fn visited(&self, x: i64, y: i64) -> bool {
+        self.visited[index(x, y)]
+    }
+}
@@ -1,85 +1,377 @@
-use std::{collections::HashMap, fmt::Display};
+pub mod damage;
+pub mod objects;
+pub mod rule_table;
+pub mod soup;
+pub mod spatial;
+pub mod sweep;
 
-use crate::{rules::RuleSet, sim::Simulation};
+use std::{collections::HashMap, fmt::Display, time::SystemTime};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use self::rule_table::{rule_table_stats, RuleTableStats};
+use crate::{
+    report::{csv_field, format_timestamp, json_array, json_number, json_string, Report},
+    rules::RuleSet,
+    sim::{RandomFill, Simulation},
+};
+
+#[derive(Clone, Debug)]
+pub struct AnalysisConfig {
+    pub iterations: usize,
+    // Side of the square region around the origin filled with a random soup
+    pub region_size: i64,
+    pub fill: RandomFill,
+    // Trial N uses seed + N
+    pub seed: u64,
+    pub trials: usize,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        AnalysisConfig {
+            iterations: 1000,
+            region_size: 256,
+            fill: RandomFill::default(),
+            seed: 0,
+            trials: 1,
+        }
+    }
+}
 
 pub struct AnalysisResult {
+    pub ruleset: String,
+    pub config: AnalysisConfig,
+    pub timestamp: SystemTime,
     pub number_of_iterations: usize,
-    // Count of iterations a cell N was a cell M
+    pub number_of_trials: usize,
+    // Percentage of iterations a cell N was a cell M, averaged across trials
     pub per_cell_type_counts: Vec<Vec<f32>>,
+    // Sample variance of the above across trials
+    pub per_cell_type_variance: Vec<Vec<f32>>,
+    // Number of cell observations per original cell type across all trials
+    pub totals: Vec<usize>,
+    pub rule_table: RuleTableStats,
+}
+
+fn write_matrix(
+    f: &mut std::fmt::Formatter<'_>,
+    matrix: &[Vec<f32>],
+    suffix: &str,
+) -> std::fmt::Result {
+    for (cell_orig, counts) in matrix.iter().enumerate() {
+        if cell_orig == 0 {
+            write!(f, "     \t")?;
+            for i in 0..counts.len() {
+                write!(f, "{}\t", i)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "    {}\t", cell_orig)?;
+        for count in counts.iter() {
+            write!(f, "{:.2}{}\t", *count, suffix)?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+// Threads to spread the given number of jobs across, one per core at most
+fn worker_count(jobs: usize) -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .clamp(1, jobs.max(1))
+}
+
+// Apply the function to every item using all available cores, keeping the order
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = worker_count(items.len());
+    let f = &f;
+    let mut results = (0..items.len()).map(|_| None).collect::<Vec<_>>();
+    std::thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|worker| {
+                scope.spawn(move || {
+                    (worker..items.len())
+                        .step_by(workers)
+                        .map(|idx| (idx, f(&items[idx])))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            for (idx, result) in handle.join().expect("Analysis thread panicked") {
+                results[idx] = Some(result);
+            }
+        }
+    });
+    results.into_iter().map(Option::unwrap).collect()
+}
+
+// Space-separated to fit in a single CSV field
+fn format_weights(weights: &[f32]) -> String {
+    weights
+        .iter()
+        .map(|w| w.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 impl Display for AnalysisResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Number of iterations: {}", self.number_of_iterations)?;
+        writeln!(f, "Number of trials: {}", self.number_of_trials)?;
         writeln!(f, "Cell counts:")?;
-        for (cell_orig, counts) in self.per_cell_type_counts.iter().enumerate() {
-            if cell_orig == 0 {
-                write!(f, "     \t")?;
-                for i in 0..counts.len() {
-                    write!(f, "{}\t", i)?;
-                }
-                writeln!(f)?;
-            }
-            write!(f, "    {}\t", cell_orig)?;
-            for count in counts.iter() {
-                write!(f, "{:.2}%\t", *count)?;
-            }
-            writeln!(f)?;
+        write_matrix(f, &self.per_cell_type_counts, "%")?;
+        if self.number_of_trials > 1 {
+            writeln!(f, "Variance:")?;
+            write_matrix(f, &self.per_cell_type_variance, "")?;
         }
-        Ok(())
+        write!(f, "{}", self.rule_table)
     }
 }
 
-pub fn analyze(ruleset: RuleSet) -> AnalysisResult {
-    let mut number_of_iterations = 0;
-    let mut per_cell_type_counts =
-        vec![vec![0usize; ruleset.cell_rules.len()]; ruleset.cell_rules.len()];
-    let mut totals = vec![0; ruleset.cell_rules.len()];
+impl Report for AnalysisResult {
+    fn to_json(&self) -> String {
+        let matrix = |m: &Vec<Vec<f32>>| json_array(m, |row| json_array(row, |v| json_number(*v)));
+        let config = [
+            format!("\"iterations\":{}", self.config.iterations),
+            format!("\"region_size\":{}", self.config.region_size),
+            format!("\"density\":{}", json_number(self.config.fill.density)),
+            format!(
+                "\"weights\":{}",
+                json_array(&self.config.fill.weights, |w| json_number(*w))
+            ),
+            format!("\"seed\":{}", self.config.seed),
+            format!("\"trials\":{}", self.config.trials),
+        ];
+        let fields = [
+            format!("\"ruleset\":{}", json_string(&self.ruleset)),
+            format!(
+                "\"timestamp\":{}",
+                json_string(&format_timestamp(self.timestamp))
+            ),
+            format!("\"config\":{{{}}}", config.join(",")),
+            format!("\"number_of_iterations\":{}", self.number_of_iterations),
+            format!("\"number_of_trials\":{}", self.number_of_trials),
+            format!("\"totals\":{}", json_array(&self.totals, |t| t.to_string())),
+            format!("\"transitions\":{}", matrix(&self.per_cell_type_counts)),
+            format!("\"variance\":{}", matrix(&self.per_cell_type_variance)),
+            format!("\"rule_table\":{}", self.rule_table.to_json()),
+        ];
+        format!("{{{}}}\n", fields.join(","))
+    }
+
+    // One row per transition, with the run parameters repeated on every row
+    fn to_csv(&self) -> String {
+        let mut result = "ruleset,timestamp,iterations,region_size,density,weights,seed,trials,\
+                          from,to,percentage,variance,total,reachable\n"
+            .to_owned();
+        let weights = format_weights(&self.config.fill.weights);
+        for (from, row) in self.per_cell_type_counts.iter().enumerate() {
+            for (to, percentage) in row.iter().enumerate() {
+                result += &format!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                    csv_field(&self.ruleset),
+                    format_timestamp(self.timestamp),
+                    self.config.iterations,
+                    self.config.region_size,
+                    self.config.fill.density,
+                    weights,
+                    self.config.seed,
+                    self.config.trials,
+                    from,
+                    to,
+                    percentage,
+                    self.per_cell_type_variance[from][to],
+                    self.totals[from],
+                    self.rule_table.reachable[from][to]
+                );
+            }
+        }
+        result
+    }
+}
 
-    const TARGET_NUMBER_OF_ITERATIONS: usize = 1000;
+// Percentages of cells of each type turning into each other type over a single soup,
+// along with the number of observations for each type.
+fn run_trial(ruleset: RuleSet, config: &AnalysisConfig, seed: u64) -> (Vec<Vec<f32>>, Vec<usize>) {
+    let cell_types_count = ruleset.cell_rules.len();
+    let mut per_cell_type_counts = vec![vec![0usize; cell_types_count]; cell_types_count];
+    let mut totals = vec![0; cell_types_count];
 
-    const TARGET_SIMULATION_SIZE_QUADRANT: i64 = 128;
+    let region_from = -config.region_size / 2;
+    let region_to = region_from + config.region_size;
 
     let mut sim = Simulation::new(ruleset);
-    sim.randomize(
-        -TARGET_SIMULATION_SIZE_QUADRANT,
-        TARGET_SIMULATION_SIZE_QUADRANT,
-        -TARGET_SIMULATION_SIZE_QUADRANT,
-        TARGET_SIMULATION_SIZE_QUADRANT,
-    );
+    sim.randomize_with(
+        region_from,
+        region_to,
+        region_from,
+        region_to,
+        &config.fill,
+        &mut StdRng::seed_from_u64(seed),
+    )
+    .expect("State weights are checked along with the configuration");
 
     let mut map = HashMap::new();
 
-    for x in -TARGET_SIMULATION_SIZE_QUADRANT..TARGET_SIMULATION_SIZE_QUADRANT {
-        for y in -TARGET_SIMULATION_SIZE_QUADRANT..TARGET_SIMULATION_SIZE_QUADRANT {
+    for x in region_from..region_to {
+        for y in region_from..region_to {
             map.insert((x, y), sim.get(x, y));
         }
     }
 
-    for _ in 0..TARGET_NUMBER_OF_ITERATIONS {
+    for _ in 0..config.iterations {
         sim.step();
-        for x in -TARGET_SIMULATION_SIZE_QUADRANT..TARGET_SIMULATION_SIZE_QUADRANT {
-            for y in -TARGET_SIMULATION_SIZE_QUADRANT..TARGET_SIMULATION_SIZE_QUADRANT {
+        for x in region_from..region_to {
+            for y in region_from..region_to {
                 let old_cell = map.get(&(x, y)).unwrap();
                 let new_cell = sim.get(x, y);
                 per_cell_type_counts[old_cell.0 as usize][new_cell.0 as usize] += 1;
                 totals[old_cell.0 as usize] += 1;
             }
         }
-        number_of_iterations += 1;
+    }
+
+    let percentages = per_cell_type_counts
+        .into_iter()
+        .enumerate()
+        .map(|(cell, counts)| {
+            counts
+                .into_iter()
+                .map(|c| c as f32 / totals[cell] as f32 * 100.0)
+                .collect()
+        })
+        .collect();
+    (percentages, totals)
+}
+
+pub fn analyze(ruleset: RuleSet, config: &AnalysisConfig) -> AnalysisResult {
+    let cell_types_count = ruleset.cell_rules.len();
+    let ruleset_name = ruleset.name.clone();
+    let (trials, trial_totals): (Vec<_>, Vec<_>) = (0..config.trials)
+        .map(|trial| {
+            run_trial(
+                ruleset.clone(),
+                config,
+                config.seed.wrapping_add(trial as u64),
+            )
+        })
+        .unzip();
+    let totals = (0..cell_types_count)
+        .map(|cell| trial_totals.iter().map(|t| t[cell]).sum())
+        .collect();
+
+    let mut mean = vec![vec![0.0f32; cell_types_count]; cell_types_count];
+    let mut variance = vec![vec![0.0f32; cell_types_count]; cell_types_count];
+    for from in 0..cell_types_count {
+        for to in 0..cell_types_count {
+            // Cell types missing from a soup have no transitions to average
+            let values = trials
+                .iter()
+                .map(|t| t[from][to])
+                .filter(|v| !v.is_nan())
+                .collect::<Vec<_>>();
+            if values.is_empty() {
+                mean[from][to] = f32::NAN;
+                variance[from][to] = f32::NAN;
+                continue;
+            }
+            let n = values.len() as f32;
+            mean[from][to] = values.iter().sum::<f32>() / n;
+            if values.len() > 1 {
+                variance[from][to] = values
+                    .iter()
+                    .map(|v| (v - mean[from][to]).powi(2))
+                    .sum::<f32>()
+                    / (n - 1.0);
+            }
+        }
     }
 
     AnalysisResult {
-        number_of_iterations,
-        per_cell_type_counts: per_cell_type_counts
-            .into_iter()
-            .enumerate()
-            .map(|(cell, counts)| {
-                counts
-                    .into_iter()
-                    .map(|c| c as f32 / totals[cell] as f32 * 100.0)
-                    .collect()
-            })
-            .collect(),
+        ruleset: ruleset_name,
+        config: config.clone(),
+        timestamp: SystemTime::now(),
+        number_of_iterations: config.iterations,
+        number_of_trials: config.trials,
+        per_cell_type_counts: mean,
+        per_cell_type_variance: variance,
+        totals,
+        rule_table: rule_table_stats(&ruleset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn small_result() -> AnalysisResult {
+        let ruleset = crate::rules::create_ruleset("life").unwrap();
+        AnalysisResult {
+            ruleset: "life".to_owned(),
+            config: AnalysisConfig {
+                iterations: 10,
+                region_size: 16,
+                fill: RandomFill {
+                    density: 0.5,
+                    weights: vec![1.0],
+                },
+                seed: 7,
+                trials: 2,
+            },
+            timestamp: UNIX_EPOCH + Duration::from_secs(1_689_338_096),
+            number_of_iterations: 10,
+            number_of_trials: 2,
+            per_cell_type_counts: vec![vec![75.0, 25.0], vec![40.0, 60.0]],
+            per_cell_type_variance: vec![vec![1.0, 1.0], vec![0.5, 0.5]],
+            totals: vec![100, 50],
+            rule_table: rule_table_stats(&ruleset),
+        }
+    }
+
+    #[test]
+    fn csv_report() {
+        let csv = small_result().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "ruleset,timestamp,iterations,region_size,density,weights,seed,trials,\
+             from,to,percentage,variance,total,reachable"
+        );
+        assert_eq!(
+            lines[2],
+            "life,2023-07-14T12:34:56Z,10,16,0.5,1,7,2,0,1,25,1,100,true"
+        );
+        let columns = lines[0].split(',').count();
+        assert!(lines.iter().all(|line| line.split(',').count() == columns));
+    }
+
+    #[test]
+    fn json_report() {
+        let json = small_result().to_json();
+        assert!(json.starts_with(
+            "{\"ruleset\":\"life\",\"timestamp\":\"2023-07-14T12:34:56Z\",\
+             \"config\":{\"iterations\":10,\"region_size\":16,\"density\":0.5,\
+             \"weights\":[1],\"seed\":7,\"trials\":2},"
+        ));
+        assert!(json.contains("\"totals\":[100,50],"));
+        assert!(json.contains("\"transitions\":[[75,25],[40,60]],"));
+        assert!(json.contains("\"variance\":[[1,1],[0.5,0.5]],"));
+        assert!(json.contains("\"rule_table\":{"));
+        assert!(json.ends_with("}\n"));
+        let depth = json.chars().try_fold(0i32, |depth, c| {
+            let depth = match c {
+                '{' | '[' => depth + 1,
+                '}' | ']' => depth - 1,
+                _ => depth,
+            };
+            (depth >= 0).then_some(depth)
+        });
+        assert_eq!(depth, Some(0));
     }
 }
@@ -0,0 +1,347 @@
+use super::*;
+
+use rand::Rng;
+
+use crate::{
+    rules::Cell,
+    space::{wrap, MAX_COORD, MIN_COORD},
+};
+
+// Rows of the damage curves shown in the text report
+const TEXT_ROWS: u64 = 20;
+
+#[derive(Clone, Debug)]
+pub struct DamageConfig {
+    pub generations: u64,
+    // Side of the square region around the origin filled with a random soup
+    pub region_size: i64,
+    pub fill: RandomFill,
+    // Trial N uses seed + N for both the soup and the flipped cell
+    pub seed: u64,
+    pub trials: usize,
+}
+
+impl Default for DamageConfig {
+    fn default() -> Self {
+        DamageConfig {
+            generations: 200,
+            region_size: 64,
+            fill: RandomFill::default(),
+            seed: 0,
+            trials: 10,
+        }
+    }
+}
+
+pub struct DamageResult {
+    pub ruleset: String,
+    pub config: DamageConfig,
+    pub timestamp: SystemTime,
+    // Per generation, averaged across trials: number of cells differing
+    // between the two copies
+    pub hamming: Vec<f32>,
+    // Distance of the furthest differing cell from the flipped one
+    pub front: Vec<f32>,
+    // Share of trials in which the copies still differ
+    pub survival: Vec<f32>,
+    // Cells per generation the front moves by, from a linear fit
+    pub velocity: f32,
+    // Slope of the logarithm of the Hamming distance, a rough analog
+    // of the largest Lyapunov exponent
+    pub exponent: f32,
+}
+
+impl Display for DamageResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Number of generations: {}", self.config.generations)?;
+        writeln!(f, "Number of trials: {}", self.config.trials)?;
+        writeln!(
+            f,
+            "Spreading velocity: {:.4} cells/generation",
+            self.velocity
+        )?;
+        writeln!(f, "Damage growth exponent: {:.4}", self.exponent)?;
+        writeln!(f, "Damage:")?;
+        writeln!(f, "    Step\tHamming\tFront\tSurvival")?;
+        let interval = (self.config.generations.div_ceil(TEXT_ROWS)).max(1) as usize;
+        for step in (0..self.hamming.len()).step_by(interval) {
+            writeln!(
+                f,
+                "    {}\t{:.1}\t{:.1}\t{:.0}%",
+                step,
+                self.hamming[step],
+                self.front[step],
+                self.survival[step] * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Report for DamageResult {
+    fn to_json(&self) -> String {
+        let curve = |values: &Vec<f32>| json_array(values, |v| json_number(*v));
+        let config = [
+            format!("\"generations\":{}", self.config.generations),
+            format!("\"region_size\":{}", self.config.region_size),
+            format!("\"density\":{}", json_number(self.config.fill.density)),
+            format!(
+                "\"weights\":{}",
+                json_array(&self.config.fill.weights, |w| json_number(*w))
+            ),
+            format!("\"seed\":{}", self.config.seed),
+            format!("\"trials\":{}", self.config.trials),
+        ];
+        let fields = [
+            format!("\"ruleset\":{}", json_string(&self.ruleset)),
+            format!(
+                "\"timestamp\":{}",
+                json_string(&format_timestamp(self.timestamp))
+            ),
+            format!("\"config\":{{{}}}", config.join(",")),
+            format!("\"velocity\":{}", json_number(self.velocity)),
+            format!("\"exponent\":{}", json_number(self.exponent)),
+            format!("\"hamming\":{}", curve(&self.hamming)),
+            format!("\"front\":{}", curve(&self.front)),
+            format!("\"survival\":{}", curve(&self.survival)),
+        ];
+        format!("{{{}}}\n", fields.join(","))
+    }
+
+    // One row per generation, with the run parameters repeated on every row
+    fn to_csv(&self) -> String {
+        let mut result = "ruleset,timestamp,generations,region_size,density,weights,seed,trials,\
+                          velocity,exponent,step,hamming,front,survival\n"
+            .to_owned();
+        for step in 0..self.hamming.len() {
+            result += &format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                csv_field(&self.ruleset),
+                format_timestamp(self.timestamp),
+                self.config.generations,
+                self.config.region_size,
+                self.config.fill.density,
+                format_weights(&self.config.fill.weights),
+                self.config.seed,
+                self.config.trials,
+                self.velocity,
+                self.exponent,
+                step,
+                self.hamming[step],
+                self.front[step],
+                self.survival[step]
+            );
+        }
+        result
+    }
+}
+
+// Hamming distance between the two copies and the distance of the furthest
+// differing cell from (x0, y0)
+fn difference(a: &Simulation, b: &Simulation, x0: i64, y0: i64) -> (usize, i64) {
+    // Cells that are zero in both copies can't differ
+    let bounds = match (a.bounding_box(), b.bounding_box()) {
+        (None, None) => return (0, 0),
+        (Some(bounds), None) | (None, Some(bounds)) => bounds,
+        (Some(a), Some(b)) => (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)),
+    };
+    let (mut hamming, mut front) = (0, 0);
+    for y in bounds.2..=bounds.3 {
+        for x in bounds.0..=bounds.1 {
+            if a.get(x, y) != b.get(x, y) {
+                hamming += 1;
+                front = front.max(wrap(x - x0).abs().max(wrap(y - y0).abs()));
+            }
+        }
+    }
+    (hamming, front)
+}
+
+// Least squares slope of the points
+fn slope(points: &[(f32, f32)]) -> f32 {
+    let n = points.len() as f32;
+    let mean_x = points.iter().map(|p| p.0).sum::<f32>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f32>() / n;
+    let covariance = points
+        .iter()
+        .map(|p| (p.0 - mean_x) * (p.1 - mean_y))
+        .sum::<f32>();
+    let variance = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum::<f32>();
+    covariance / variance
+}
+
+struct Trial {
+    hamming: Vec<usize>,
+    front: Vec<i64>,
+}
+
+fn run_trial(ruleset: RuleSet, config: &DamageConfig, seed: u64) -> Trial {
+    let region_from = -config.region_size / 2;
+    let region_to = region_from + config.region_size;
+    let states = ruleset.cell_rules.len();
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut original = Simulation::new(ruleset);
+    original
+        .randomize_with(
+            region_from,
+            region_to,
+            region_from,
+            region_to,
+            &config.fill,
+            &mut rng,
+        )
+        .expect("State weights are checked along with the configuration");
+    // Flip a random cell of the soup to another state
+    let mut damaged = original.clone();
+    let x0 = rng.gen_range(region_from..region_to);
+    let y0 = rng.gen_range(region_from..region_to);
+    let cell = damaged.get(x0, y0).0 as usize;
+    let flipped = (cell + rng.gen_range(1..states)) % states;
+    damaged.set(x0, y0, Cell(flipped as u8));
+
+    let mut trial = Trial {
+        hamming: vec![1],
+        front: vec![0],
+    };
+    for _ in 0..config.generations {
+        original.step();
+        damaged.step();
+        let (hamming, front) = difference(&original, &damaged, x0, y0);
+        trial.hamming.push(hamming);
+        trial.front.push(front);
+    }
+    trial
+}
+
+// Run pairs of simulations differing by a single cell and follow
+// how the difference spreads
+pub fn damage(ruleset: RuleSet, config: &DamageConfig) -> DamageResult {
+    let ruleset_name = ruleset.name.clone();
+    let seeds = (0..config.trials)
+        .map(|trial| config.seed.wrapping_add(trial as u64))
+        .collect::<Vec<_>>();
+    let trials = parallel_map(&seeds, |seed| run_trial(ruleset.clone(), config, *seed));
+
+    let steps = config.generations as usize + 1;
+    let trials_count = trials.len().max(1) as f32;
+    let mean = |f: &dyn Fn(&Trial, usize) -> f32| {
+        (0..steps)
+            .map(|step| trials.iter().map(|t| f(t, step)).sum::<f32>() / trials_count)
+            .collect::<Vec<_>>()
+    };
+    let hamming = mean(&|t, step| t.hamming[step] as f32);
+    let front = mean(&|t, step| t.front[step] as f32);
+    let survival = mean(&|t, step| if t.hamming[step] > 0 { 1.0 } else { 0.0 });
+
+    // Fit each trial while the damage lasts and before the front wraps
+    // around the space, then average the fits
+    let max_front = (MAX_COORD - MIN_COORD) / 2;
+    let fits = trials
+        .iter()
+        .filter_map(|t| {
+            let points = (0..steps)
+                .take_while(|step| t.hamming[*step] > 0 && t.front[*step] < max_front)
+                .map(|step| (step as f32, t.front[step] as f32, t.hamming[step] as f32))
+                .collect::<Vec<_>>();
+            if points.len() < 2 {
+                return None;
+            }
+            let velocity = slope(&points.iter().map(|p| (p.0, p.1)).collect::<Vec<_>>());
+            let exponent = slope(&points.iter().map(|p| (p.0, p.2.ln())).collect::<Vec<_>>());
+            Some((velocity, exponent))
+        })
+        .collect::<Vec<_>>();
+    let (velocity, exponent) = if fits.is_empty() {
+        (0.0, 0.0)
+    } else {
+        let n = fits.len() as f32;
+        (
+            fits.iter().map(|f| f.0).sum::<f32>() / n,
+            fits.iter().map(|f| f.1).sum::<f32>() / n,
+        )
+    };
+
+    DamageResult {
+        ruleset: ruleset_name,
+        config: config.clone(),
+        timestamp: SystemTime::now(),
+        hamming,
+        front,
+        survival,
+        velocity,
+        exponent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::create_ruleset;
+
+    fn config(density: f32) -> DamageConfig {
+        DamageConfig {
+            generations: 30,
+            region_size: 32,
+            fill: RandomFill {
+                density,
+                weights: vec![],
+            },
+            seed: 3,
+            trials: 2,
+        }
+    }
+
+    #[test]
+    fn identical_runs() {
+        let mut original = Simulation::new(create_ruleset("life").unwrap());
+        original
+            .randomize_with(
+                -16,
+                16,
+                -16,
+                16,
+                &config(0.4).fill,
+                &mut StdRng::seed_from_u64(1),
+            )
+            .unwrap();
+        let mut copy = original.clone();
+        for _ in 0..20 {
+            original.step();
+            copy.step();
+            assert_eq!(difference(&original, &copy, 0, 0), (0, 0));
+        }
+    }
+
+    #[test]
+    fn differences() {
+        let ruleset = create_ruleset("life").unwrap();
+        let (mut a, b) = (Simulation::new(ruleset.clone()), Simulation::new(ruleset));
+        a.set(3, -2, Cell(1));
+        assert_eq!(difference(&a, &b, 0, 0), (1, 3));
+        // The front is measured across the edge of the space
+        a.set(3, -2, Cell(0));
+        a.set(MAX_COORD, 0, Cell(1));
+        assert_eq!(difference(&a, &b, MIN_COORD, 0), (1, 1));
+        assert_eq!(slope(&[(0.0, 1.0), (1.0, 3.0), (2.0, 5.0)]), 2.0);
+    }
+
+    #[test]
+    fn single_cell_dies_out() {
+        // The only live cell is the flipped one, it dies in a generation
+        let result = damage(create_ruleset("life").unwrap(), &config(0.0));
+        assert_eq!(result.hamming[..3], [1.0, 0.0, 0.0]);
+        assert_eq!(result.survival[..3], [1.0, 0.0, 0.0]);
+        assert_eq!((result.velocity, result.exponent), (0.0, 0.0));
+    }
+
+    #[test]
+    fn seeded_trials() {
+        let ruleset = create_ruleset("life").unwrap();
+        let first = damage(ruleset.clone(), &config(0.4));
+        let second = damage(ruleset, &config(0.4));
+        assert_eq!(first.hamming, second.hamming);
+        assert_eq!(first.front, second.front);
+        assert_eq!(first.hamming.len(), 31);
+    }
+}
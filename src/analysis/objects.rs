@@ -0,0 +1,382 @@
+use super::*;
+
+use std::collections::BTreeMap;
+
+use crate::{
+    pattern::Pattern,
+    periodicity::{PeriodDetector, Periodicity},
+    rules::Cell,
+    space::{wrap, MAX_COORD, MIN_COORD},
+};
+
+// Objects are run in isolation for at most this many generations
+// to find their period
+const MAX_PERIOD: u64 = 1000;
+// Parts of an object that don't work on their own are merged
+// with the parts up to this many cells away
+const MAX_MERGE_DISTANCE: i64 = 3;
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObjectKind {
+    StillLife,
+    Oscillator(u64),
+    Spaceship(Periodicity),
+    // Doesn't become periodic when run on its own
+    Unknown,
+}
+
+impl Display for ObjectKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjectKind::StillLife => write!(f, "still life"),
+            ObjectKind::Oscillator(period) => write!(f, "oscillator (p{})", period),
+            ObjectKind::Spaceship(periodicity) => write!(f, "spaceship ({})", periodicity.speed()),
+            ObjectKind::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Object {
+    // Canonical name following apgcodes, e.g. xs4_33 for the block
+    pub code: String,
+    pub kind: ObjectKind,
+}
+
+// Cells of a group of objects, coordinates are unwrapped so that
+// a group crossing the edge of the space stays contiguous
+pub type Cells = Vec<(i64, i64, Cell)>;
+
+pub fn live_cells(sim: &Simulation) -> Cells {
+    let Some((x_min, x_max, y_min, y_max)) = sim.bounding_box() else {
+        return vec![];
+    };
+    let mut cells = vec![];
+    for y in y_min..=y_max {
+        for x in x_min..=x_max {
+            let cell = sim.get(x, y);
+            if cell.0 != 0 {
+                cells.push((x, y, cell));
+            }
+        }
+    }
+    cells
+}
+
+// Split the cells into groups where each cell is at most `distance`
+// cells away from another one in its group (in both directions)
+pub fn connected_components(cells: &[(i64, i64, Cell)], distance: i64) -> Vec<Cells> {
+    let index = cells
+        .iter()
+        .enumerate()
+        .map(|(idx, &(x, y, _))| ((wrap(x), wrap(y)), idx))
+        .collect::<HashMap<_, _>>();
+    let mut visited = vec![false; cells.len()];
+    let mut components = vec![];
+    for start in 0..cells.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut component = vec![cells[start]];
+        let mut queue = vec![(cells[start].0, cells[start].1)];
+        while let Some((x, y)) = queue.pop() {
+            for dy in -distance..=distance {
+                for dx in -distance..=distance {
+                    let Some(&idx) = index.get(&(wrap(x + dx), wrap(y + dy))) else {
+                        continue;
+                    };
+                    if visited[idx] {
+                        continue;
+                    }
+                    visited[idx] = true;
+                    component.push((x + dx, y + dy, cells[idx].2));
+                    queue.push((x + dx, y + dy));
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+// Bounds of the cells as (x_min, x_max, y_min, y_max), inclusive
+pub fn bounds(cells: &[(i64, i64, Cell)]) -> (i64, i64, i64, i64) {
+    cells.iter().fold(
+        (i64::MAX, i64::MIN, i64::MAX, i64::MIN),
+        |(x_min, x_max, y_min, y_max), &(x, y, _)| {
+            (x_min.min(x), x_max.max(x), y_min.min(y), y_max.max(y))
+        },
+    )
+}
+
+// Number of empty cells between two boxes, taking wrapping into account
+pub fn bounds_distance(a: (i64, i64, i64, i64), b: (i64, i64, i64, i64)) -> i64 {
+    let size = MAX_COORD - MIN_COORD + 1;
+    let gap = |a_min: i64, a_max: i64, b_min: i64, b_max: i64| {
+        [-size, 0, size]
+            .iter()
+            .map(|shift| {
+                (b_min + shift - a_max - 1)
+                    .max(a_min - b_max - shift - 1)
+                    .max(0)
+            })
+            .min()
+            .unwrap()
+    };
+    gap(a.0, a.1, b.0, b.1).max(gap(a.2, a.3, b.2, b.3))
+}
+
+// Extended Wechsler format: the pattern is cut into strips of 5 rows, each
+// column of a strip is written as a base-32 digit and strips are separated
+// by 'z'. Runs of zeros are shortened to w (2), x (3) and yN (4 to 39).
+fn wechsler(pattern: &Pattern, state: Cell) -> String {
+    let mut strips = vec![];
+    for strip_y in (0..pattern.height()).step_by(5) {
+        let columns = (0..pattern.width())
+            .map(|x| {
+                (0..5)
+                    .filter(|row| pattern.get(x, strip_y + row) == state)
+                    .map(|row| 1 << row)
+                    .sum::<usize>()
+            })
+            .collect::<Vec<_>>();
+        let mut strip = String::new();
+        let mut zeros = 0;
+        for column in columns {
+            if column == 0 {
+                zeros += 1;
+                continue;
+            }
+            while zeros > 0 {
+                let run = zeros.min(39);
+                match run {
+                    1 => strip.push('0'),
+                    2 => strip.push('w'),
+                    3 => strip.push('x'),
+                    _ => {
+                        strip.push('y');
+                        strip.push(DIGITS[run - 4] as char);
+                    }
+                }
+                zeros -= run;
+            }
+            strip.push(DIGITS[column] as char);
+        }
+        strips.push(strip);
+    }
+    strips.join("z")
+}
+
+// Multi-state patterns get one layer per state, separated by '_'
+fn encode(pattern: &Pattern) -> String {
+    (1..=pattern.max_state())
+        .map(|state| wechsler(pattern, Cell(state)))
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+// The shortest and then alphabetically first code among all phases and orientations
+fn canonical_code(phases: &[Pattern]) -> String {
+    let mut codes = vec![];
+    for phase in phases {
+        let mut pattern = phase.clone();
+        for _ in 0..4 {
+            codes.push(encode(&pattern.flipped()));
+            codes.push(encode(&pattern));
+            pattern = pattern.rotated();
+        }
+    }
+    codes
+        .into_iter()
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default()
+}
+
+// Run the pattern on its own until it repeats
+pub fn classify(ruleset: &RuleSet, pattern: &Pattern) -> Object {
+    let mut sim = Simulation::new(ruleset.clone());
+    pattern.place_centered(&mut sim);
+    let mut detector = PeriodDetector::new(true);
+    detector.observe(&sim);
+    let mut phases = vec![Pattern::from_universe(&sim)];
+    let periodicity = loop {
+        if sim.current_step() >= MAX_PERIOD {
+            break None;
+        }
+        sim.step();
+        if let Some(periodicity) = detector.observe(&sim) {
+            break Some(periodicity);
+        }
+        phases.push(Pattern::from_universe(&sim));
+    };
+    let kind = match periodicity {
+        // Whatever it turns into, the object itself is not periodic
+        Some(periodicity) if periodicity.start != 0 => ObjectKind::Unknown,
+        None => ObjectKind::Unknown,
+        Some(periodicity) if periodicity.is_spaceship() => ObjectKind::Spaceship(periodicity),
+        Some(periodicity) if periodicity.period == 1 => ObjectKind::StillLife,
+        Some(periodicity) => ObjectKind::Oscillator(periodicity.period),
+    };
+    let code = match kind {
+        ObjectKind::StillLife => format!("xs{}_{}", pattern.population(), canonical_code(&phases)),
+        ObjectKind::Oscillator(period) => format!("xp{}_{}", period, canonical_code(&phases)),
+        ObjectKind::Spaceship(periodicity) => {
+            format!("xq{}_{}", periodicity.period, canonical_code(&phases))
+        }
+        ObjectKind::Unknown => "zz_UNKNOWN".to_owned(),
+    };
+    Object { code, kind }
+}
+
+// Classifies objects, remembering the results for patterns seen before
+pub struct Classifier {
+    ruleset: RuleSet,
+    cache: HashMap<Pattern, Object>,
+}
+
+impl Classifier {
+    pub fn new(ruleset: RuleSet) -> Self {
+        Classifier {
+            ruleset,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn ruleset(&self) -> &RuleSet {
+        &self.ruleset
+    }
+
+    pub fn classify(&mut self, cells: &[(i64, i64, Cell)]) -> Object {
+        let pattern =
+            Pattern::from_cells(cells.iter().copied()).expect("Objects fit into the space");
+        if let Some(object) = self.cache.get(&pattern) {
+            return object.clone();
+        }
+        let object = classify(&self.ruleset, &pattern);
+        self.cache.insert(pattern, object.clone());
+        object
+    }
+
+    // Split the cells into objects. Touching cells make up an object unless it
+    // doesn't work on its own, then it is merged with nearby objects.
+    pub fn separate(&mut self, cells: &[(i64, i64, Cell)]) -> Vec<(Cells, Object)> {
+        let mut objects = connected_components(cells, 1)
+            .into_iter()
+            .map(|component| {
+                let object = self.classify(&component);
+                (component, object)
+            })
+            .collect::<Vec<_>>();
+        for distance in 2..=MAX_MERGE_DISTANCE {
+            // Broken parts are first combined with each other (e.g. the two
+            // halves of a beacon) and only then with anything around them
+            self.merge(&mut objects, distance, true);
+            self.merge(&mut objects, distance, false);
+        }
+        objects
+    }
+
+    fn merge(&mut self, objects: &mut Vec<(Cells, Object)>, distance: i64, unknown_only: bool) {
+        if objects.iter().all(|(_, o)| o.kind != ObjectKind::Unknown) {
+            return;
+        }
+        let cells = objects
+            .iter()
+            .filter(|(_, object)| !unknown_only || object.kind == ObjectKind::Unknown)
+            .flat_map(|(cells, _)| cells.iter().copied())
+            .collect::<Vec<_>>();
+        let groups = connected_components(&cells, distance);
+        let group_of = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, group)| {
+                group
+                    .iter()
+                    .map(move |&(x, y, _)| ((wrap(x), wrap(y)), idx))
+            })
+            .collect::<HashMap<_, _>>();
+        let first_cell_group =
+            |cells: &Cells| group_of.get(&(wrap(cells[0].0), wrap(cells[0].1))).copied();
+        let mut merged = BTreeMap::new();
+        for (cells, object) in objects.iter() {
+            if object.kind != ObjectKind::Unknown {
+                continue;
+            }
+            let idx = first_cell_group(cells).unwrap();
+            if merged.contains_key(&idx) {
+                continue;
+            }
+            let merged_object = self.classify(&groups[idx]);
+            if !unknown_only || merged_object.kind != ObjectKind::Unknown {
+                merged.insert(idx, merged_object);
+            }
+        }
+        objects.retain(|(cells, _)| {
+            !first_cell_group(cells).is_some_and(|idx| merged.contains_key(&idx))
+        });
+        for (idx, object) in merged {
+            objects.push((groups[idx].clone(), object));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pattern::plaintext, rules::create_ruleset};
+
+    fn object(text: &str) -> Object {
+        classify(
+            &create_ruleset("life").unwrap(),
+            &plaintext::parse(text).unwrap(),
+        )
+    }
+
+    #[test]
+    fn apgcodes() {
+        assert_eq!(object("OO\nOO\n").code, "xs4_33");
+        assert_eq!(object("OOO\n").code, "xp2_7");
+        assert_eq!(object(".O\n..O\nOOO\n").code, "xq4_153");
+        assert_eq!(object(".OO\nO..O\n.OO\n").code, "xs6_696");
+        assert_eq!(object("O\n").code, "zz_UNKNOWN");
+
+        assert_eq!(object("OO\nOO\n").kind, ObjectKind::StillLife);
+        assert_eq!(object("OOO\n").kind, ObjectKind::Oscillator(2));
+        assert!(matches!(
+            object(".O\n..O\nOOO\n").kind,
+            ObjectKind::Spaceship(_)
+        ));
+    }
+
+    #[test]
+    fn orientation_invariant() {
+        let ruleset = create_ruleset("life").unwrap();
+        // Glider, loaf and a phase of the toad, none of them symmetric
+        for text in [".O\n..O\nOOO\n", ".OO\nO..O\n.O.O\n..O\n", ".OOO\nOOO.\n"] {
+            let mut pattern = plaintext::parse(text).unwrap();
+            let code = classify(&ruleset, &pattern).code;
+            for _ in 0..4 {
+                assert_eq!(classify(&ruleset, &pattern).code, code);
+                assert_eq!(classify(&ruleset, &pattern.flipped()).code, code);
+                pattern = pattern.rotated();
+            }
+        }
+    }
+
+    #[test]
+    fn separate() {
+        let mut classifier = Classifier::new(create_ruleset("life").unwrap());
+        let cells = [(0, 0), (1, 0), (0, 1), (1, 1), (10, 0), (11, 0), (12, 0)]
+            .map(|(x, y)| (x, y, Cell(1)));
+        let mut codes = classifier
+            .separate(&cells)
+            .into_iter()
+            .map(|(_, object)| object.code)
+            .collect::<Vec<_>>();
+        codes.sort();
+        assert_eq!(codes, ["xp2_7", "xs4_33"]);
+    }
+}
@@ -0,0 +1,258 @@
+use super::*;
+
+use rand::Rng;
+
+use crate::{rules::Cell, space::Space};
+
+// Rules with more neighborhood configurations than this are sampled
+const MAX_CONFIGURATIONS: usize = 1 << 22;
+const SAMPLES: usize = 1 << 18;
+
+#[derive(Clone, Debug, Default)]
+pub struct StateTableStats {
+    pub configurations: usize,
+    // Configurations turning the cell into the quiescent state 0
+    pub to_quiescent: usize,
+    // Configurations leaving the cell as it is
+    pub unchanged: usize,
+    // Configurations handled by a default rather than an explicit transition
+    pub fall_through: usize,
+}
+
+// Statistics of the rule table itself, computed by applying the rules to
+// every neighborhood configuration rather than by running a simulation
+#[derive(Clone, Debug)]
+pub struct RuleTableStats {
+    // Whether configurations were sampled rather than enumerated
+    pub sampled: bool,
+    pub per_state: Vec<StateTableStats>,
+    // Whether a cell of state N can turn into a cell of state M
+    pub reachable: Vec<Vec<bool>>,
+}
+
+fn share(count: usize, total: usize) -> f32 {
+    count as f32 / total as f32
+}
+
+impl RuleTableStats {
+    fn total(&self, f: fn(&StateTableStats) -> usize) -> usize {
+        self.per_state.iter().map(f).sum()
+    }
+
+    pub fn configurations(&self) -> usize {
+        self.total(|s| s.configurations)
+    }
+
+    // Langton's lambda: share of transitions into a non-quiescent state
+    pub fn lambda(&self) -> f32 {
+        1.0 - share(self.total(|s| s.to_quiescent), self.configurations())
+    }
+
+    // Share of transitions into the quiescent state 0, 1 - lambda
+    pub fn quiescent(&self) -> f32 {
+        share(self.total(|s| s.to_quiescent), self.configurations())
+    }
+
+    // Share of transitions leaving the cell as it is
+    pub fn unchanged(&self) -> f32 {
+        share(self.total(|s| s.unchanged), self.configurations())
+    }
+
+    // Share of configurations with an explicit transition
+    pub fn completeness(&self) -> f32 {
+        1.0 - share(self.total(|s| s.fall_through), self.configurations())
+    }
+
+    // States that no cell of another state can turn into
+    pub fn never_produced(&self) -> Vec<usize> {
+        (0..self.reachable.len())
+            .filter(|to| {
+                (0..self.reachable.len()).all(|from| from == *to || !self.reachable[from][*to])
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        let per_state = json_array(&self.per_state, |s| {
+            format!(
+                "{{\"configurations\":{},\"lambda\":{},\"unchanged\":{},\"completeness\":{}}}",
+                s.configurations,
+                json_number(1.0 - share(s.to_quiescent, s.configurations)),
+                json_number(share(s.unchanged, s.configurations)),
+                json_number(1.0 - share(s.fall_through, s.configurations))
+            )
+        });
+        let fields = [
+            format!("\"configurations\":{}", self.configurations()),
+            format!("\"sampled\":{}", self.sampled),
+            format!("\"lambda\":{}", json_number(self.lambda())),
+            format!("\"quiescent\":{}", json_number(self.quiescent())),
+            format!("\"unchanged\":{}", json_number(self.unchanged())),
+            format!("\"completeness\":{}", json_number(self.completeness())),
+            format!("\"per_state\":{}", per_state),
+            format!(
+                "\"reachable\":{}",
+                json_array(&self.reachable, |row| json_array(row, |r| r.to_string()))
+            ),
+            format!(
+                "\"never_produced\":{}",
+                json_array(&self.never_produced(), |s| s.to_string())
+            ),
+        ];
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+impl Display for RuleTableStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Rule table ({} {} neighborhoods):",
+            if self.sampled { "sampled" } else { "all" },
+            self.configurations()
+        )?;
+        writeln!(f, "    Langton's lambda: {:.4}", self.lambda())?;
+        writeln!(
+            f,
+            "    Transitions to the quiescent state: {:.2}%",
+            self.quiescent() * 100.0
+        )?;
+        writeln!(
+            f,
+            "    Transitions leaving the cell unchanged: {:.2}%",
+            self.unchanged() * 100.0
+        )?;
+        writeln!(
+            f,
+            "    Completeness (no default used): {:.2}%",
+            self.completeness() * 100.0
+        )?;
+        writeln!(f, "    State\tLambda\tUnchanged\tComplete\tTurns into")?;
+        for (state, stats) in self.per_state.iter().enumerate() {
+            let targets = (0..self.reachable.len())
+                .filter(|to| self.reachable[state][*to])
+                .map(|to| to.to_string())
+                .collect::<Vec<_>>();
+            writeln!(
+                f,
+                "    {}\t{:.4}\t{:.2}%\t{:.2}%\t{}",
+                state,
+                1.0 - share(stats.to_quiescent, stats.configurations),
+                share(stats.unchanged, stats.configurations) * 100.0,
+                (1.0 - share(stats.fall_through, stats.configurations)) * 100.0,
+                targets.join(" ")
+            )?;
+        }
+        let never_produced = self
+            .never_produced()
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        if !never_produced.is_empty() {
+            writeln!(
+                f,
+                "    Never produced from other states: {}",
+                never_produced.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn rule_table_stats(ruleset: &RuleSet) -> RuleTableStats {
+    let states = ruleset.cell_rules.len();
+    let neighborhood = ruleset.neighborhood;
+
+    let mut space = Space::new();
+    let mut positions = vec![];
+    space.iterate_neighbors(neighborhood, 0, 0, |x, y| {
+        if (x, y) != (0, 0) {
+            positions.push((x, y));
+        }
+    });
+
+    let configurations = (states as u64)
+        .checked_pow(positions.len() as u32)
+        .map(|n| n as usize)
+        .filter(|n| n * states <= MAX_CONFIGURATIONS);
+    let sampled = configurations.is_none();
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let mut per_state = vec![StateTableStats::default(); states];
+    let mut reachable = vec![vec![false; states]; states];
+    for (state, rule) in ruleset.cell_rules.iter().enumerate() {
+        let cell = Cell(state as u8);
+        space.set_curr(0, 0, cell);
+        for idx in 0..configurations.unwrap_or(SAMPLES) {
+            // Configuration index as base-N digits, one per neighbor
+            let mut digits = idx;
+            for (x, y) in positions.iter() {
+                let neighbor = if sampled {
+                    rng.gen_range(0..states)
+                } else {
+                    let neighbor = digits % states;
+                    digits /= states;
+                    neighbor
+                };
+                space.set_curr(*x, *y, Cell(neighbor as u8));
+            }
+            let new_cell = rule.apply(0, 0, cell, &space, neighborhood);
+            let stats = &mut per_state[state];
+            stats.configurations += 1;
+            if new_cell.0 == 0 {
+                stats.to_quiescent += 1;
+            }
+            if new_cell == cell {
+                stats.unchanged += 1;
+            }
+            if rule.falls_through(0, 0, cell, &space, neighborhood) {
+                stats.fall_through += 1;
+            }
+            if let Some(targets) = reachable[state].get_mut(new_cell.0 as usize) {
+                *targets = true;
+            }
+        }
+    }
+
+    RuleTableStats {
+        sampled,
+        per_state,
+        reachable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::create_ruleset;
+
+    #[test]
+    fn life() {
+        let stats = rule_table_stats(&create_ruleset("life").unwrap());
+        assert!(!stats.sampled);
+        assert_eq!(stats.configurations(), 512);
+        // 56 births with 3 neighbors, 28 + 56 survivals with 2 or 3
+        assert_eq!(stats.lambda(), 140.0 / 512.0);
+        assert_eq!(stats.quiescent(), 372.0 / 512.0);
+        assert_eq!(stats.unchanged(), (200.0 + 84.0) / 512.0);
+        assert_eq!(stats.completeness(), 1.0);
+        assert_eq!(stats.reachable, [[true, true], [true, true]]);
+        assert!(stats.never_produced().is_empty());
+    }
+
+    #[test]
+    fn byl_completeness() {
+        let stats = rule_table_stats(&create_ruleset("byl").unwrap());
+        assert!(!stats.sampled);
+        assert_eq!(stats.configurations(), 7usize.pow(5));
+        // Only the neighborhoods in the transition table, in any rotation,
+        // are explicit, all others fall back to the default rules
+        let explicit: usize = stats
+            .per_state
+            .iter()
+            .map(|s| s.configurations - s.fall_through)
+            .sum();
+        assert_eq!(explicit, 136);
+        assert_eq!(stats.completeness(), 1.0 - share(16807 - 136, 16807));
+    }
+}
@@ -0,0 +1,372 @@
+use super::*;
+
+use super::objects::{
+    bounds, bounds_distance, connected_components, live_cells, Classifier, Object, ObjectKind,
+};
+use crate::{periodicity::PeriodDetector, rules::Cell};
+
+// Spaceships this far from everything else are taken out of the soup
+// before they wrap around the space and crash back into it
+const ESCAPE_DISTANCE: i64 = 16;
+const ESCAPE_CHECK_INTERVAL: u64 = 32;
+// Number of seeds kept for each object
+const SAMPLE_SEEDS: usize = 3;
+
+#[derive(Clone, Debug)]
+pub struct SoupConfig {
+    pub soups: usize,
+    // Side of the square random soup
+    pub soup_size: i64,
+    pub fill: RandomFill,
+    // Soup N uses seed + N
+    pub seed: u64,
+    // Soups still changing after this many generations are censused as they are
+    pub max_generations: u64,
+}
+
+impl Default for SoupConfig {
+    fn default() -> Self {
+        SoupConfig {
+            soups: 1000,
+            soup_size: 16,
+            fill: RandomFill {
+                density: 0.5,
+                weights: vec![],
+            },
+            seed: 0,
+            max_generations: 5000,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CensusEntry {
+    pub object: Object,
+    pub count: usize,
+    // Seeds of the first soups the object was found in
+    pub sample_seeds: Vec<u64>,
+}
+
+#[derive(Default)]
+struct Census {
+    entries: HashMap<String, CensusEntry>,
+    unstabilized: usize,
+}
+
+impl Census {
+    fn add(&mut self, object: &Object, seed: u64) {
+        let entry = self
+            .entries
+            .entry(object.code.clone())
+            .or_insert_with(|| CensusEntry {
+                object: object.clone(),
+                count: 0,
+                sample_seeds: vec![],
+            });
+        entry.count += 1;
+        if !entry.sample_seeds.contains(&seed) {
+            entry.sample_seeds.push(seed);
+            entry.sample_seeds.sort();
+            entry.sample_seeds.truncate(SAMPLE_SEEDS);
+        }
+    }
+
+    fn merge(&mut self, other: Census) {
+        for (code, other_entry) in other.entries {
+            let entry = self.entries.entry(code).or_insert_with(|| CensusEntry {
+                object: other_entry.object.clone(),
+                count: 0,
+                sample_seeds: vec![],
+            });
+            entry.count += other_entry.count;
+            entry.sample_seeds.extend(other_entry.sample_seeds);
+            entry.sample_seeds.sort();
+            entry.sample_seeds.dedup();
+            entry.sample_seeds.truncate(SAMPLE_SEEDS);
+        }
+        self.unstabilized += other.unstabilized;
+    }
+}
+
+pub struct SoupResult {
+    pub ruleset: String,
+    pub config: SoupConfig,
+    pub timestamp: SystemTime,
+    // Soups that didn't stabilize within the generation limit
+    pub unstabilized: usize,
+    // Most common objects first
+    pub census: Vec<CensusEntry>,
+}
+
+fn format_seeds(seeds: &[u64], separator: &str) -> String {
+    seeds
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+impl Display for SoupResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Number of soups: {}", self.config.soups)?;
+        writeln!(
+            f,
+            "Not stabilized after {} generations: {}",
+            self.config.max_generations, self.unstabilized
+        )?;
+        writeln!(
+            f,
+            "Number of objects: {}",
+            self.census.iter().map(|e| e.count).sum::<usize>()
+        )?;
+        writeln!(f, "Census:")?;
+        writeln!(f, "    Count\tCode\tType\tSample seeds")?;
+        for entry in &self.census {
+            writeln!(
+                f,
+                "    {}\t{}\t{}\t{}",
+                entry.count,
+                entry.object.code,
+                entry.object.kind,
+                format_seeds(&entry.sample_seeds, ", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Report for SoupResult {
+    fn to_json(&self) -> String {
+        let config = [
+            format!("\"soups\":{}", self.config.soups),
+            format!("\"soup_size\":{}", self.config.soup_size),
+            format!("\"density\":{}", json_number(self.config.fill.density)),
+            format!(
+                "\"weights\":{}",
+                json_array(&self.config.fill.weights, |w| json_number(*w))
+            ),
+            format!("\"seed\":{}", self.config.seed),
+            format!("\"max_generations\":{}", self.config.max_generations),
+        ];
+        let census = json_array(&self.census, |entry| {
+            format!(
+                "{{\"code\":{},\"type\":{},\"count\":{},\"sample_seeds\":{}}}",
+                json_string(&entry.object.code),
+                json_string(&entry.object.kind.to_string()),
+                entry.count,
+                json_array(&entry.sample_seeds, |s| s.to_string())
+            )
+        });
+        let fields = [
+            format!("\"ruleset\":{}", json_string(&self.ruleset)),
+            format!(
+                "\"timestamp\":{}",
+                json_string(&format_timestamp(self.timestamp))
+            ),
+            format!("\"config\":{{{}}}", config.join(",")),
+            format!("\"unstabilized\":{}", self.unstabilized),
+            format!("\"census\":{}", census),
+        ];
+        format!("{{{}}}\n", fields.join(","))
+    }
+
+    // One row per object, with the run parameters repeated on every row
+    fn to_csv(&self) -> String {
+        let mut result = "ruleset,timestamp,soups,soup_size,density,weights,seed,max_generations,\
+                          code,type,count,sample_seeds\n"
+            .to_owned();
+        for entry in &self.census {
+            result += &format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                csv_field(&self.ruleset),
+                format_timestamp(self.timestamp),
+                self.config.soups,
+                self.config.soup_size,
+                self.config.fill.density,
+                format_weights(&self.config.fill.weights),
+                self.config.seed,
+                self.config.max_generations,
+                csv_field(&entry.object.code),
+                csv_field(&entry.object.kind.to_string()),
+                entry.count,
+                format_seeds(&entry.sample_seeds, " ")
+            );
+        }
+        result
+    }
+}
+
+// Census and erase spaceships that got far enough from the rest of the soup
+fn remove_escaped_spaceships(
+    sim: &mut Simulation,
+    classifier: &mut Classifier,
+    census: &mut Census,
+    seed: u64,
+) -> bool {
+    let components = connected_components(&live_cells(sim), 1);
+    let component_bounds = components.iter().map(|c| bounds(c)).collect::<Vec<_>>();
+    let mut removed = false;
+    for (idx, component) in components.iter().enumerate() {
+        let escaped = component_bounds.iter().enumerate().all(|(other, b)| {
+            other == idx || bounds_distance(component_bounds[idx], *b) > ESCAPE_DISTANCE
+        });
+        if !escaped {
+            continue;
+        }
+        let object = classifier.classify(component);
+        if let ObjectKind::Spaceship(_) = object.kind {
+            census.add(&object, seed);
+            for &(x, y, _) in component {
+                sim.set(x, y, Cell(0));
+            }
+            removed = true;
+        }
+    }
+    removed
+}
+
+// Run a single soup until it stabilizes and census the remains
+fn run_soup(classifier: &mut Classifier, config: &SoupConfig, seed: u64, census: &mut Census) {
+    let soup_from = -config.soup_size / 2;
+    let soup_to = soup_from + config.soup_size;
+
+    let mut sim = Simulation::new(classifier.ruleset().clone());
+    sim.randomize_with(
+        soup_from,
+        soup_to,
+        soup_from,
+        soup_to,
+        &config.fill,
+        &mut StdRng::seed_from_u64(seed),
+    )
+    .expect("State weights are checked along with the configuration");
+
+    let mut detector = PeriodDetector::new(false);
+    detector.observe(&sim);
+    let mut stabilized = false;
+    while sim.current_step() < config.max_generations {
+        sim.step();
+        if sim.current_step() % ESCAPE_CHECK_INTERVAL == 0
+            && remove_escaped_spaceships(&mut sim, classifier, census, seed)
+        {
+            detector.reset();
+            detector.observe(&sim);
+            continue;
+        }
+        if detector.observe(&sim).is_some() {
+            stabilized = true;
+            break;
+        }
+    }
+    if !stabilized {
+        census.unstabilized += 1;
+    }
+
+    for (_, object) in classifier.separate(&live_cells(&sim)) {
+        census.add(&object, seed);
+    }
+}
+
+// Run many random soups, split what remains of them into separate objects
+// and count how often each object appears. Soups are spread across threads,
+// each one keeping its own classifier so that its cache is reused.
+pub fn search(ruleset: RuleSet, config: &SoupConfig) -> SoupResult {
+    let ruleset_name = ruleset.name.clone();
+    let workers = worker_count(config.soups);
+    let censuses = parallel_map(&(0..workers).collect::<Vec<_>>(), |worker| {
+        let mut classifier = Classifier::new(ruleset.clone());
+        let mut census = Census::default();
+        for soup in (*worker..config.soups).step_by(workers) {
+            let seed = config.seed.wrapping_add(soup as u64);
+            run_soup(&mut classifier, config, seed, &mut census);
+        }
+        census
+    });
+    let mut census = Census::default();
+    for worker_census in censuses {
+        census.merge(worker_census);
+    }
+
+    let mut entries = census.entries.into_values().collect::<Vec<_>>();
+    entries.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.object.code.cmp(&b.object.code))
+    });
+    SoupResult {
+        ruleset: ruleset_name,
+        config: config.clone(),
+        timestamp: SystemTime::now(),
+        unstabilized: census.unstabilized,
+        census: entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::create_ruleset;
+
+    fn config(soups: usize, soup_size: i64, density: f32) -> SoupConfig {
+        SoupConfig {
+            soups,
+            soup_size,
+            fill: RandomFill {
+                density,
+                weights: vec![],
+            },
+            seed: 42,
+            max_generations: 300,
+        }
+    }
+
+    fn census(result: &SoupResult) -> Vec<(String, usize, Vec<u64>)> {
+        result
+            .census
+            .iter()
+            .map(|e| (e.object.code.clone(), e.count, e.sample_seeds.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn full_soups_are_blocks() {
+        let result = search(create_ruleset("life").unwrap(), &config(5, 2, 1.0));
+        assert_eq!(result.unstabilized, 0);
+        assert_eq!(
+            census(&result),
+            [("xs4_33".to_owned(), 5, vec![42, 43, 44])]
+        );
+    }
+
+    #[test]
+    fn seeded_search() {
+        let ruleset = create_ruleset("life").unwrap();
+        let first = search(ruleset.clone(), &config(4, 8, 0.5));
+        let second = search(ruleset, &config(4, 8, 0.5));
+        assert_eq!(census(&first), census(&second));
+        assert_eq!(first.unstabilized, second.unstabilized);
+        assert!(first
+            .census
+            .windows(2)
+            .all(|pair| pair[0].count >= pair[1].count));
+    }
+
+    #[test]
+    fn escaped_glider() {
+        let ruleset = create_ruleset("life").unwrap();
+        let mut sim = Simulation::new(ruleset.clone());
+        for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            sim.set(x, y, Cell(1));
+        }
+        let mut classifier = Classifier::new(ruleset);
+        let mut census = Census::default();
+        assert!(remove_escaped_spaceships(
+            &mut sim,
+            &mut classifier,
+            &mut census,
+            7
+        ));
+        assert_eq!(sim.bounding_box(), None);
+        assert_eq!(census.entries["xq4_153"].sample_seeds, [7]);
+    }
+}
@@ -0,0 +1,557 @@
+use super::*;
+
+use super::objects::connected_components;
+use crate::{
+    rules::Cell,
+    space::{Space, MAX_COORD, MIN_COORD},
+};
+
+const SIDE: usize = (MAX_COORD - MIN_COORD + 1) as usize;
+// Blocks are keyed by their cells packed into 8 bits each
+pub const MAX_BLOCK_SIZE: usize = 4;
+
+#[derive(Clone, Debug)]
+pub struct SpatialConfig {
+    pub generations: u64,
+    // Metrics are computed every this many generations
+    pub interval: u64,
+    // Side of the square region around the origin filled with a random soup
+    pub region_size: i64,
+    pub fill: RandomFill,
+    // Trial N uses seed + N
+    pub seed: u64,
+    pub trials: usize,
+    // Block entropy is computed for blocks of 1x1 up to this size
+    pub block_size: usize,
+    // Correlations are computed for cells up to this many cells apart
+    pub max_distance: usize,
+}
+
+impl Default for SpatialConfig {
+    fn default() -> Self {
+        SpatialConfig {
+            generations: 500,
+            interval: 50,
+            region_size: 256,
+            fill: RandomFill::default(),
+            seed: 0,
+            trials: 1,
+            block_size: 3,
+            max_distance: 16,
+        }
+    }
+}
+
+// Metrics of a single generation, averaged across trials
+#[derive(Clone, Debug)]
+pub struct SpatialSample {
+    pub step: u64,
+    // Share of cells in each state
+    pub density: Vec<f32>,
+    // Shannon entropy of the NxN blocks in bits per cell, for N from 1 up
+    pub block_entropy: Vec<f32>,
+    // Per non-zero state, correlation between cells 1, 2, ... cells apart
+    // being in that state: 1 when they always agree, 0 when independent
+    pub correlation: Vec<Vec<f32>>,
+    // Per non-zero state, distance at which the correlation drops below 1/e,
+    // capped at the largest distance measured
+    pub correlation_length: Vec<f32>,
+    // Groups of touching non-zero cells
+    pub clusters: f32,
+    pub mean_cluster_size: f32,
+    pub largest_cluster: f32,
+    // Number of clusters of 1, 2-3, 4-7, ... cells
+    pub cluster_sizes: Vec<f32>,
+}
+
+pub struct SpatialResult {
+    pub ruleset: String,
+    pub config: SpatialConfig,
+    pub timestamp: SystemTime,
+    pub samples: Vec<SpatialSample>,
+}
+
+fn size_range(bin: usize) -> String {
+    match bin {
+        0 => "1".to_owned(),
+        _ => format!("{}-{}", 1 << bin, (2 << bin) - 1),
+    }
+}
+
+impl Display for SpatialResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Number of generations: {}", self.config.generations)?;
+        writeln!(f, "Number of trials: {}", self.config.trials)?;
+        writeln!(f, "Samples:")?;
+        write!(f, "    Step\tDensity\t")?;
+        for size in 1..=self.config.block_size {
+            write!(f, "H({}x{})\t", size, size)?;
+        }
+        writeln!(f, "Clusters\tMean size\tLargest")?;
+        for sample in &self.samples {
+            write!(
+                f,
+                "    {}\t{:.4}\t",
+                sample.step,
+                1.0 - sample.density.first().copied().unwrap_or(1.0)
+            )?;
+            for entropy in &sample.block_entropy {
+                write!(f, "{:.4}\t", entropy)?;
+            }
+            writeln!(
+                f,
+                "{:.1}\t{:.2}\t{:.1}",
+                sample.clusters, sample.mean_cluster_size, sample.largest_cluster
+            )?;
+        }
+
+        let Some(last) = self.samples.last() else {
+            return Ok(());
+        };
+        writeln!(f, "Two-point correlation at step {}:", last.step)?;
+        write!(f, "    Distance")?;
+        for state in 1..=last.correlation.len() {
+            write!(f, "\tState {}", state)?;
+        }
+        writeln!(f)?;
+        for distance in 0..self.config.max_distance {
+            write!(f, "    {}", distance + 1)?;
+            for correlation in &last.correlation {
+                write!(f, "\t{:.4}", correlation[distance])?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "    Length")?;
+        for length in &last.correlation_length {
+            write!(f, "\t{:.2}", length)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Cluster sizes at step {}:", last.step)?;
+        writeln!(f, "    Size\tCount")?;
+        for (bin, count) in last.cluster_sizes.iter().enumerate() {
+            writeln!(f, "    {}\t{:.1}", size_range(bin), count)?;
+        }
+        Ok(())
+    }
+}
+
+impl Report for SpatialResult {
+    fn to_json(&self) -> String {
+        let values = |v: &Vec<f32>| json_array(v, |v| json_number(*v));
+        let config = [
+            format!("\"generations\":{}", self.config.generations),
+            format!("\"interval\":{}", self.config.interval),
+            format!("\"region_size\":{}", self.config.region_size),
+            format!("\"density\":{}", json_number(self.config.fill.density)),
+            format!("\"weights\":{}", values(&self.config.fill.weights)),
+            format!("\"seed\":{}", self.config.seed),
+            format!("\"trials\":{}", self.config.trials),
+            format!("\"block_size\":{}", self.config.block_size),
+            format!("\"max_distance\":{}", self.config.max_distance),
+        ];
+        let samples = json_array(&self.samples, |s| {
+            let fields = [
+                format!("\"step\":{}", s.step),
+                format!("\"density\":{}", values(&s.density)),
+                format!("\"block_entropy\":{}", values(&s.block_entropy)),
+                format!("\"correlation\":{}", json_array(&s.correlation, values)),
+                format!("\"correlation_length\":{}", values(&s.correlation_length)),
+                format!("\"clusters\":{}", json_number(s.clusters)),
+                format!("\"mean_cluster_size\":{}", json_number(s.mean_cluster_size)),
+                format!("\"largest_cluster\":{}", json_number(s.largest_cluster)),
+                format!("\"cluster_sizes\":{}", values(&s.cluster_sizes)),
+            ];
+            format!("{{{}}}", fields.join(","))
+        });
+        let fields = [
+            format!("\"ruleset\":{}", json_string(&self.ruleset)),
+            format!(
+                "\"timestamp\":{}",
+                json_string(&format_timestamp(self.timestamp))
+            ),
+            format!("\"config\":{{{}}}", config.join(",")),
+            format!("\"samples\":{}", samples),
+        ];
+        format!("{{{}}}\n", fields.join(","))
+    }
+
+    // One row per sample, with the run parameters repeated on every row.
+    // Correlations and cluster sizes are space-separated lists.
+    fn to_csv(&self) -> String {
+        let Some(first) = self.samples.first() else {
+            return String::new();
+        };
+        let mut result = "ruleset,timestamp,generations,interval,region_size,density,weights,\
+                          seed,trials,step"
+            .to_owned();
+        for state in 0..first.density.len() {
+            result += &format!(",density_{}", state);
+        }
+        for size in 1..=first.block_entropy.len() {
+            result += &format!(",entropy_{}x{}", size, size);
+        }
+        for state in 1..=first.correlation.len() {
+            result += &format!(",correlation_{},correlation_length_{}", state, state);
+        }
+        result += ",clusters,mean_cluster_size,largest_cluster,cluster_sizes\n";
+
+        for sample in &self.samples {
+            result += &format!(
+                "{},{},{},{},{},{},{},{},{},{}",
+                csv_field(&self.ruleset),
+                format_timestamp(self.timestamp),
+                self.config.generations,
+                self.config.interval,
+                self.config.region_size,
+                self.config.fill.density,
+                format_weights(&self.config.fill.weights),
+                self.config.seed,
+                self.config.trials,
+                sample.step
+            );
+            for density in &sample.density {
+                result += &format!(",{}", density);
+            }
+            for entropy in &sample.block_entropy {
+                result += &format!(",{}", entropy);
+            }
+            for (correlation, length) in sample
+                .correlation
+                .iter()
+                .zip(sample.correlation_length.iter())
+            {
+                result += &format!(",{},{}", format_weights(correlation), length);
+            }
+            result += &format!(
+                ",{},{},{},{}\n",
+                sample.clusters,
+                sample.mean_cluster_size,
+                sample.largest_cluster,
+                format_weights(&sample.cluster_sizes)
+            );
+        }
+        result
+    }
+}
+
+// States of all cells of the space, row by row from the top left corner
+struct Grid(Vec<u8>);
+
+impl Grid {
+    fn new(space: &Space) -> Self {
+        let mut cells = Vec::with_capacity(SIDE * SIDE);
+        for y in MIN_COORD..=MAX_COORD {
+            for x in MIN_COORD..=MAX_COORD {
+                cells.push(space.get(x, y).0);
+            }
+        }
+        Grid(cells)
+    }
+
+    // Coordinates wrap around like in the space
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.0[(y % SIDE) * SIDE + x % SIDE]
+    }
+}
+
+// Shannon entropy of the distribution of all (overlapping) blocks
+// of the given size, in bits per cell
+fn block_entropy(grid: &Grid, size: usize) -> f32 {
+    let mut counts = HashMap::new();
+    for y in 0..SIDE {
+        for x in 0..SIDE {
+            let mut block = 0u128;
+            for dy in 0..size {
+                for dx in 0..size {
+                    block = block << 8 | grid.get(x + dx, y + dy) as u128;
+                }
+            }
+            *counts.entry(block).or_insert(0usize) += 1;
+        }
+    }
+    // Summed in a fixed order, the map's order would change the rounding
+    // from run to run
+    let mut counts = counts.into_values().collect::<Vec<_>>();
+    counts.sort_unstable();
+    let total = (SIDE * SIDE) as f32;
+    let entropy = counts
+        .iter()
+        .map(|count| {
+            let p = *count as f32 / total;
+            p * (1.0 / p).log2()
+        })
+        .sum::<f32>();
+    entropy / (size * size) as f32
+}
+
+// Correlation of cells being in the given state as a function of distance,
+// averaged over horizontal and vertical pairs
+fn correlation(grid: &Grid, state: u8, density: f32, max_distance: usize) -> Vec<f32> {
+    let variance = density - density * density;
+    (1..=max_distance)
+        .map(|distance| {
+            if variance <= 0.0 {
+                return f32::NAN;
+            }
+            let mut pairs = 0usize;
+            for y in 0..SIDE {
+                for x in 0..SIDE {
+                    if grid.get(x, y) != state {
+                        continue;
+                    }
+                    pairs += (grid.get(x + distance, y) == state) as usize;
+                    pairs += (grid.get(x, y + distance) == state) as usize;
+                }
+            }
+            let both = pairs as f32 / (2 * SIDE * SIDE) as f32;
+            (both - density * density) / variance
+        })
+        .collect()
+}
+
+// Interpolated distance at which the correlation drops below 1/e
+fn correlation_length(correlation: &[f32]) -> f32 {
+    let threshold = 1.0 / std::f32::consts::E;
+    let mut previous = 1.0;
+    for (idx, value) in correlation.iter().enumerate() {
+        if value.is_nan() {
+            return f32::NAN;
+        }
+        if *value < threshold {
+            return idx as f32 + (previous - threshold) / (previous - value);
+        }
+        previous = *value;
+    }
+    correlation.len() as f32
+}
+
+pub fn spatial_sample(space: &Space, states: usize, config: &SpatialConfig) -> SpatialSample {
+    let grid = Grid::new(space);
+    let total = (SIDE * SIDE) as f32;
+
+    let mut counts = vec![0usize; states];
+    let mut cells = vec![];
+    for (idx, cell) in grid.0.iter().enumerate() {
+        counts[*cell as usize] += 1;
+        if *cell != 0 {
+            let (x, y) = ((idx % SIDE) as i64, (idx / SIDE) as i64);
+            cells.push((x + MIN_COORD, y + MIN_COORD, Cell(*cell)));
+        }
+    }
+    let density = counts
+        .iter()
+        .map(|count| *count as f32 / total)
+        .collect::<Vec<_>>();
+
+    let correlation = (1..states)
+        .map(|state| correlation(&grid, state as u8, density[state], config.max_distance))
+        .collect::<Vec<_>>();
+
+    let sizes = connected_components(&cells, 1)
+        .iter()
+        .map(|cluster| cluster.len())
+        .collect::<Vec<_>>();
+    let mut cluster_sizes = vec![];
+    for size in sizes.iter() {
+        let bin = size.ilog2() as usize;
+        if cluster_sizes.len() <= bin {
+            cluster_sizes.resize(bin + 1, 0.0);
+        }
+        cluster_sizes[bin] += 1.0;
+    }
+
+    SpatialSample {
+        step: space.current_step(),
+        density,
+        block_entropy: (1..=config.block_size)
+            .map(|size| block_entropy(&grid, size))
+            .collect(),
+        correlation_length: correlation.iter().map(|c| correlation_length(c)).collect(),
+        correlation,
+        clusters: sizes.len() as f32,
+        mean_cluster_size: if sizes.is_empty() {
+            0.0
+        } else {
+            cells.len() as f32 / sizes.len() as f32
+        },
+        largest_cluster: sizes.iter().max().copied().unwrap_or(0) as f32,
+        cluster_sizes,
+    }
+}
+
+fn run_trial(ruleset: RuleSet, config: &SpatialConfig, seed: u64) -> Vec<SpatialSample> {
+    let region_from = -config.region_size / 2;
+    let region_to = region_from + config.region_size;
+    let states = ruleset.cell_rules.len();
+
+    let mut sim = Simulation::new(ruleset);
+    sim.randomize_with(
+        region_from,
+        region_to,
+        region_from,
+        region_to,
+        &config.fill,
+        &mut StdRng::seed_from_u64(seed),
+    )
+    .expect("State weights are checked along with the configuration");
+
+    let mut samples = vec![spatial_sample(sim.space(), states, config)];
+    while sim.current_step() < config.generations {
+        sim.step();
+        if sim.current_step() % config.interval == 0 {
+            samples.push(spatial_sample(sim.space(), states, config));
+        }
+    }
+    samples
+}
+
+// Mean of the values that are known (not NaN)
+fn mean(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = values
+        .filter(|v| !v.is_nan())
+        .fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        f32::NAN
+    } else {
+        sum / count as f32
+    }
+}
+
+// Element-wise mean of the samples of the same step, shorter
+// cluster size lists count as having no larger clusters
+fn mean_sample(samples: &[&SpatialSample]) -> SpatialSample {
+    let first = samples[0];
+    let values = |f: &dyn Fn(&SpatialSample) -> &Vec<f32>, len: usize| {
+        (0..len)
+            .map(|idx| {
+                mean(
+                    samples
+                        .iter()
+                        .map(|s| f(s).get(idx).copied().unwrap_or(0.0)),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let cluster_bins = samples
+        .iter()
+        .map(|s| s.cluster_sizes.len())
+        .max()
+        .unwrap_or(0);
+    SpatialSample {
+        step: first.step,
+        density: values(&|s| &s.density, first.density.len()),
+        block_entropy: values(&|s| &s.block_entropy, first.block_entropy.len()),
+        correlation: (0..first.correlation.len())
+            .map(|state| values(&|s| &s.correlation[state], first.correlation[state].len()))
+            .collect(),
+        correlation_length: values(&|s| &s.correlation_length, first.correlation_length.len()),
+        clusters: mean(samples.iter().map(|s| s.clusters)),
+        mean_cluster_size: mean(samples.iter().map(|s| s.mean_cluster_size)),
+        largest_cluster: mean(samples.iter().map(|s| s.largest_cluster)),
+        cluster_sizes: values(&|s| &s.cluster_sizes, cluster_bins),
+    }
+}
+
+// Follow block entropy, correlations and cluster sizes of random soups
+// as they evolve
+pub fn spatial(ruleset: RuleSet, config: &SpatialConfig) -> SpatialResult {
+    let ruleset_name = ruleset.name.clone();
+    let seeds = (0..config.trials)
+        .map(|trial| config.seed.wrapping_add(trial as u64))
+        .collect::<Vec<_>>();
+    let trials = parallel_map(&seeds, |seed| run_trial(ruleset.clone(), config, *seed));
+
+    let steps = trials.iter().map(|t| t.len()).min().unwrap_or(0);
+    let samples = (0..steps)
+        .map(|idx| mean_sample(&trials.iter().map(|t| &t[idx]).collect::<Vec<_>>()))
+        .collect();
+
+    SpatialResult {
+        ruleset: ruleset_name,
+        config: config.clone(),
+        timestamp: SystemTime::now(),
+        samples,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::create_ruleset;
+
+    fn grid(f: impl Fn(i64, i64) -> bool) -> Grid {
+        let mut space = Space::new();
+        for y in MIN_COORD..=MAX_COORD {
+            for x in MIN_COORD..=MAX_COORD {
+                space.set_curr(x, y, Cell(f(x, y) as u8));
+            }
+        }
+        Grid::new(&space)
+    }
+
+    #[test]
+    fn block_entropies() {
+        let empty = grid(|_, _| false);
+        assert_eq!(block_entropy(&empty, 1), 0.0);
+        assert_eq!(block_entropy(&empty, 3), 0.0);
+
+        // Two equally likely blocks of any size
+        let checkerboard = grid(|x, y| (x + y) % 2 == 0);
+        assert_eq!(block_entropy(&checkerboard, 1), 1.0);
+        assert_eq!(block_entropy(&checkerboard, 2), 0.25);
+
+        // Four equally likely 2x2 blocks
+        let stripes = grid(|x, _| x.rem_euclid(4) < 2);
+        assert_eq!(block_entropy(&stripes, 1), 1.0);
+        assert_eq!(block_entropy(&stripes, 2), 0.5);
+    }
+
+    #[test]
+    fn correlations() {
+        let checkerboard = grid(|x, y| (x + y) % 2 == 0);
+        assert_eq!(correlation(&checkerboard, 1, 0.5, 2), [-1.0, 1.0]);
+        assert!(correlation(&grid(|_, _| false), 1, 0.0, 1)[0].is_nan());
+
+        assert_eq!(correlation_length(&[1.0, 1.0]), 2.0);
+        assert_eq!(correlation_length(&[0.0]), 1.0 - 1.0 / std::f32::consts::E);
+        assert!(correlation_length(&[f32::NAN]).is_nan());
+    }
+
+    #[test]
+    fn clusters() {
+        let mut space = Space::new();
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1), (10, 0), (11, 0), (12, 0)] {
+            space.set_curr(x, y, Cell(1));
+        }
+        let sample = spatial_sample(&space, 2, &SpatialConfig::default());
+        assert_eq!(sample.density[1], 7.0 / 65536.0);
+        assert_eq!(sample.clusters, 2.0);
+        assert_eq!(sample.mean_cluster_size, 3.5);
+        assert_eq!(sample.largest_cluster, 4.0);
+        assert_eq!(sample.cluster_sizes, [0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn seeded_trials() {
+        let config = SpatialConfig {
+            generations: 20,
+            interval: 10,
+            region_size: 32,
+            trials: 2,
+            max_distance: 4,
+            ..SpatialConfig::default()
+        };
+        let run = || spatial(create_ruleset("life").unwrap(), &config);
+        let (first, second) = (run(), run());
+        assert_eq!(first.samples.len(), 3);
+        assert_eq!(
+            first.samples.iter().map(|s| s.step).collect::<Vec<_>>(),
+            [0, 10, 20]
+        );
+        // NaN correlations compare unequal, their formatting doesn't
+        assert_eq!(
+            format!("{:?}", first.samples),
+            format!("{:?}", second.samples)
+        );
+    }
+}
@@ -0,0 +1,558 @@
+use super::*;
+
+use std::collections::HashSet;
+
+use log::info;
+use rand::Rng;
+
+use super::objects::live_cells;
+use crate::{
+    periodicity::{PeriodDetector, Periodicity},
+    rules::{
+        life::life_like_name,
+        totalistic::{table_size, totalistic},
+        Cell,
+    },
+};
+
+// Share of the soup changing every generation above which a rule
+// that never settles down is considered chaotic rather than complex
+const CHAOTIC_ACTIVITY: f32 = 0.1;
+const LIFE_LIKE_RULES: usize = 1 << 18;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum RuleFamily {
+    // Two-state outer totalistic rules in the B/S notation
+    LifeLike,
+    // Random totalistic tables, see rules::totalistic
+    Totalistic,
+}
+
+impl Display for RuleFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleFamily::LifeLike => write!(f, "life-like"),
+            RuleFamily::Totalistic => write!(f, "totalistic"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SweepConfig {
+    pub family: RuleFamily,
+    // Number of states of totalistic rules
+    pub states: usize,
+    // Number of rules to sample, all of them if not set (life-like only)
+    pub rules: Option<usize>,
+    pub region_size: i64,
+    pub fill: RandomFill,
+    pub generations: u64,
+    // Every rule runs trials with seeds seed, seed + 1, ...
+    pub seed: u64,
+    pub trials: usize,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        SweepConfig {
+            family: RuleFamily::LifeLike,
+            states: 3,
+            rules: Some(100),
+            region_size: 64,
+            fill: RandomFill {
+                density: 0.5,
+                weights: vec![],
+            },
+            generations: 300,
+            seed: 0,
+            trials: 3,
+        }
+    }
+}
+
+// Classes of behavior following Wolfram
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum WolframClass {
+    // Dies out or turns uniform
+    Homogeneous = 1,
+    // Settles into static or periodic structures
+    Periodic = 2,
+    // Keeps changing most of the space
+    Chaotic = 3,
+    // Keeps changing, but only locally
+    Complex = 4,
+}
+
+impl WolframClass {
+    pub const ALL: [WolframClass; 4] = [
+        WolframClass::Homogeneous,
+        WolframClass::Periodic,
+        WolframClass::Chaotic,
+        WolframClass::Complex,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WolframClass::Homogeneous => "homogeneous",
+            WolframClass::Periodic => "periodic",
+            WolframClass::Chaotic => "chaotic",
+            WolframClass::Complex => "complex",
+        }
+    }
+}
+
+impl Display for WolframClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", *self as u8, self.name())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RuleMetrics {
+    pub rule: String,
+    pub class: WolframClass,
+    // Share of the soup cells changing per generation
+    pub activity: f32,
+    // Final population relative to the initial one, or to a single cell
+    // for soups that start out empty
+    pub growth: f32,
+    // Shannon entropy of the final states in the soup region, in bits per cell
+    pub entropy: f32,
+    // Longest period, if every trial became periodic
+    pub period: Option<u64>,
+}
+
+pub struct SweepResult {
+    pub config: SweepConfig,
+    pub timestamp: SystemTime,
+    // Most complex behavior first
+    pub rules: Vec<RuleMetrics>,
+}
+
+impl SweepResult {
+    pub fn class_counts(&self) -> Vec<(WolframClass, usize)> {
+        WolframClass::ALL
+            .iter()
+            .map(|class| {
+                (
+                    *class,
+                    self.rules.iter().filter(|r| r.class == *class).count(),
+                )
+            })
+            .collect()
+    }
+}
+
+fn format_period(period: Option<u64>) -> String {
+    period.map_or("-".to_owned(), |p| p.to_string())
+}
+
+impl Display for SweepResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Rule family: {}", self.config.family)?;
+        writeln!(f, "Number of rules: {}", self.rules.len())?;
+        writeln!(f, "Number of trials: {}", self.config.trials)?;
+        writeln!(f, "Classes:")?;
+        for (class, count) in self.class_counts() {
+            writeln!(f, "    {}\t{}", class, count)?;
+        }
+        writeln!(f, "Rules:")?;
+        writeln!(
+            f,
+            "    Rank\tRule\tClass\tActivity\tGrowth\tEntropy\tPeriod"
+        )?;
+        for (rank, rule) in self.rules.iter().enumerate() {
+            writeln!(
+                f,
+                "    {}\t{}\t{}\t{:.4}\t{:.2}\t{:.3}\t{}",
+                rank + 1,
+                rule.rule,
+                rule.class,
+                rule.activity,
+                rule.growth,
+                rule.entropy,
+                format_period(rule.period)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Report for SweepResult {
+    fn to_json(&self) -> String {
+        let config = [
+            format!(
+                "\"family\":{}",
+                json_string(&self.config.family.to_string())
+            ),
+            format!("\"states\":{}", self.config.states),
+            format!(
+                "\"rules\":{}",
+                self.config
+                    .rules
+                    .map_or("null".to_owned(), |n| n.to_string())
+            ),
+            format!("\"region_size\":{}", self.config.region_size),
+            format!("\"density\":{}", json_number(self.config.fill.density)),
+            format!(
+                "\"weights\":{}",
+                json_array(&self.config.fill.weights, |w| json_number(*w))
+            ),
+            format!("\"generations\":{}", self.config.generations),
+            format!("\"seed\":{}", self.config.seed),
+            format!("\"trials\":{}", self.config.trials),
+        ];
+        let classes = self
+            .class_counts()
+            .iter()
+            .map(|(class, count)| format!("\"{}\":{}", class.name(), count))
+            .collect::<Vec<_>>();
+        let rules = json_array(&self.rules, |rule| {
+            format!(
+                "{{\"rule\":{},\"class\":{},\"class_name\":{},\"activity\":{},\
+                 \"growth\":{},\"entropy\":{},\"period\":{}}}",
+                json_string(&rule.rule),
+                rule.class as u8,
+                json_string(rule.class.name()),
+                json_number(rule.activity),
+                json_number(rule.growth),
+                json_number(rule.entropy),
+                rule.period.map_or("null".to_owned(), |p| p.to_string())
+            )
+        });
+        let fields = [
+            format!(
+                "\"timestamp\":{}",
+                json_string(&format_timestamp(self.timestamp))
+            ),
+            format!("\"config\":{{{}}}", config.join(",")),
+            format!("\"classes\":{{{}}}", classes.join(",")),
+            format!("\"rules\":{}", rules),
+        ];
+        format!("{{{}}}\n", fields.join(","))
+    }
+
+    // One row per rule in ranking order, with the run parameters repeated on every row
+    fn to_csv(&self) -> String {
+        let mut result = "timestamp,family,region_size,density,weights,generations,seed,trials,\
+                          rank,rule,class,class_name,activity,growth,entropy,period\n"
+            .to_owned();
+        for (rank, rule) in self.rules.iter().enumerate() {
+            result += &format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                format_timestamp(self.timestamp),
+                self.config.family,
+                self.config.region_size,
+                self.config.fill.density,
+                format_weights(&self.config.fill.weights),
+                self.config.generations,
+                self.config.seed,
+                self.config.trials,
+                rank + 1,
+                csv_field(&rule.rule),
+                rule.class as u8,
+                rule.class.name(),
+                rule.activity,
+                rule.growth,
+                rule.entropy,
+                rule.period.map_or(String::new(), |p| p.to_string())
+            );
+        }
+        result
+    }
+}
+
+// Names of the rules to run, usable with rules::create_ruleset
+pub fn generate_rules(config: &SweepConfig) -> Result<Vec<String>, String> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let life_like_name = |bits: usize| life_like_name(bits as u16 & 0x1ff, (bits >> 9) as u16);
+    match (config.family, config.rules) {
+        (RuleFamily::LifeLike, None) => Ok((0..LIFE_LIKE_RULES).map(life_like_name).collect()),
+        (RuleFamily::LifeLike, Some(count)) if count >= LIFE_LIKE_RULES => {
+            Ok((0..LIFE_LIKE_RULES).map(life_like_name).collect())
+        }
+        (RuleFamily::LifeLike, Some(count)) => {
+            let mut seen = HashSet::new();
+            while seen.len() < count {
+                seen.insert(rng.gen_range(0..LIFE_LIKE_RULES));
+            }
+            let mut bits = seen.into_iter().collect::<Vec<_>>();
+            bits.sort();
+            Ok(bits.into_iter().map(life_like_name).collect())
+        }
+        (RuleFamily::Totalistic, None) => {
+            Err("Only life-like rules can be enumerated, please set the number of rules".to_owned())
+        }
+        (RuleFamily::Totalistic, Some(count)) => {
+            let states = config.states;
+            if !(2..=36).contains(&states) {
+                return Err("Totalistic rules need between 2 and 36 states".to_owned());
+            }
+            // Each rule gets its own share of transitions to non-zero states,
+            // with the empty neighborhood kept empty
+            Ok((0..count)
+                .map(|_| {
+                    let lambda = rng.gen::<f32>();
+                    let tables = (0..states)
+                        .map(|state| {
+                            (0..table_size(states))
+                                .map(|sum| {
+                                    if (state, sum) == (0, 0) || rng.gen::<f32>() >= lambda {
+                                        Cell(0)
+                                    } else {
+                                        Cell(rng.gen_range(1..states) as u8)
+                                    }
+                                })
+                                .collect()
+                        })
+                        .collect::<Vec<_>>();
+                    totalistic(&tables).name
+                })
+                .collect())
+        }
+    }
+}
+
+struct TrialMetrics {
+    activity: f32,
+    growth: f32,
+    entropy: f32,
+    periodicity: Option<Periodicity>,
+    population: usize,
+}
+
+impl TrialMetrics {
+    fn class(&self) -> WolframClass {
+        match self.periodicity {
+            _ if self.population == 0 => WolframClass::Homogeneous,
+            Some(periodicity) if periodicity.period == 1 && self.entropy == 0.0 => {
+                WolframClass::Homogeneous
+            }
+            Some(_) => WolframClass::Periodic,
+            None if self.activity >= CHAOTIC_ACTIVITY => WolframClass::Chaotic,
+            None => WolframClass::Complex,
+        }
+    }
+}
+
+fn run_trial(ruleset: RuleSet, config: &SweepConfig, seed: u64) -> TrialMetrics {
+    let region_from = -config.region_size / 2;
+    let region_to = region_from + config.region_size;
+    let region = |sim: &Simulation| {
+        (region_from..region_to)
+            .flat_map(|y| (region_from..region_to).map(move |x| sim.get(x, y)))
+            .collect::<Vec<_>>()
+    };
+
+    let cell_types_count = ruleset.cell_rules.len();
+    let mut sim = Simulation::new(ruleset);
+    sim.randomize_with(
+        region_from,
+        region_to,
+        region_from,
+        region_to,
+        &config.fill,
+        &mut StdRng::seed_from_u64(seed),
+    )
+    .expect("State weights are checked along with the configuration");
+    let initial_population = live_cells(&sim).len();
+
+    let mut detector = PeriodDetector::new(false);
+    detector.observe(&sim);
+    let mut previous = region(&sim);
+    let mut changes = vec![];
+    let mut periodicity = None;
+    while sim.current_step() < config.generations {
+        sim.step();
+        let current = region(&sim);
+        changes.push(
+            previous
+                .iter()
+                .zip(&current)
+                .filter(|(a, b)| a != b)
+                .count(),
+        );
+        previous = current;
+        periodicity = detector.observe(&sim);
+        if periodicity.is_some() {
+            break;
+        }
+    }
+
+    // Activity of the cycle if there is one, of the second half of the run otherwise
+    let measured = match periodicity {
+        Some(periodicity) => periodicity.period as usize,
+        None => changes.len().div_ceil(2),
+    };
+    let measured = &changes[changes.len() - measured.min(changes.len())..];
+    let cells = (config.region_size * config.region_size) as f32;
+    let activity = measured.iter().sum::<usize>() as f32 / measured.len().max(1) as f32 / cells;
+
+    let mut counts = vec![0usize; cell_types_count];
+    for cell in &previous {
+        counts[cell.0 as usize] += 1;
+    }
+    let entropy = counts
+        .iter()
+        .filter(|c| **c != 0)
+        .map(|c| {
+            let p = *c as f32 / cells;
+            p * (1.0 / p).log2()
+        })
+        .sum::<f32>();
+
+    let population = live_cells(&sim).len();
+    TrialMetrics {
+        activity,
+        // An empty soup can still grow with B0 rules, count it as a single cell
+        growth: population as f32 / initial_population.max(1) as f32,
+        entropy,
+        periodicity,
+        population,
+    }
+}
+
+fn measure(rule: &str, config: &SweepConfig) -> RuleMetrics {
+    let ruleset = crate::rules::create_ruleset(rule).expect("Generated an invalid rule");
+    let trials = (0..config.trials)
+        .map(|trial| {
+            run_trial(
+                ruleset.clone(),
+                config,
+                config.seed.wrapping_add(trial as u64),
+            )
+        })
+        .collect::<Vec<_>>();
+    // Majority vote, ties go to the more complex class
+    let class = WolframClass::ALL
+        .iter()
+        .copied()
+        .max_by_key(|class| {
+            (
+                trials.iter().filter(|t| t.class() == *class).count(),
+                *class,
+            )
+        })
+        .unwrap();
+    let mean = |f: fn(&TrialMetrics) -> f32| {
+        trials.iter().map(f).sum::<f32>() / trials.len().max(1) as f32
+    };
+    let period = trials
+        .iter()
+        .map(|t| t.periodicity.map(|p| p.period))
+        .collect::<Option<Vec<_>>>()
+        .and_then(|periods| periods.into_iter().max());
+    RuleMetrics {
+        rule: rule.to_owned(),
+        class,
+        activity: mean(|t| t.activity),
+        growth: mean(|t| t.growth),
+        entropy: mean(|t| t.entropy),
+        period,
+    }
+}
+
+// Run every rule of the family on the same soups and classify its behavior
+pub fn sweep(config: &SweepConfig) -> Result<SweepResult, String> {
+    let rules = generate_rules(config)?;
+    info!("Sweeping {} rules", rules.len());
+    let mut rules = parallel_map(&rules, |rule| measure(rule, config));
+    rules.sort_by(|a, b| {
+        b.class
+            .cmp(&a.class)
+            .then_with(|| b.entropy.total_cmp(&a.entropy))
+            .then_with(|| a.rule.cmp(&b.rule))
+    });
+    Ok(SweepResult {
+        config: config.clone(),
+        timestamp: SystemTime::now(),
+        rules,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(family: RuleFamily, rules: Option<usize>) -> SweepConfig {
+        SweepConfig {
+            family,
+            rules,
+            region_size: 16,
+            generations: 50,
+            trials: 2,
+            ..SweepConfig::default()
+        }
+    }
+
+    #[test]
+    fn generated_rules() {
+        let all = generate_rules(&config(RuleFamily::LifeLike, None)).unwrap();
+        assert_eq!(all.len(), LIFE_LIKE_RULES);
+        assert_eq!(all[0], "B/S");
+
+        let sample = generate_rules(&config(RuleFamily::LifeLike, Some(20))).unwrap();
+        assert_eq!(sample.len(), 20);
+        assert_eq!(
+            sample,
+            generate_rules(&config(RuleFamily::LifeLike, Some(20))).unwrap()
+        );
+        assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 20);
+
+        let totalistic = generate_rules(&config(RuleFamily::Totalistic, Some(3))).unwrap();
+        assert_eq!(totalistic.len(), 3);
+        assert!(totalistic
+            .iter()
+            .all(|rule| crate::rules::create_ruleset(rule).is_some()));
+        assert!(generate_rules(&config(RuleFamily::Totalistic, None)).is_err());
+        let one_state = SweepConfig {
+            states: 1,
+            ..config(RuleFamily::Totalistic, Some(3))
+        };
+        assert!(generate_rules(&one_state).is_err());
+    }
+
+    #[test]
+    fn classes() {
+        let config = config(RuleFamily::LifeLike, None);
+        // Everything dies in the first generation
+        let dies = measure("B/S", &config);
+        assert_eq!(dies.class, WolframClass::Homogeneous);
+        assert_eq!((dies.growth, dies.entropy, dies.activity), (0.0, 0.0, 0.0));
+        assert_eq!(dies.period, Some(1));
+        // Nothing ever changes
+        let frozen = measure("B/S012345678", &config);
+        assert_eq!(frozen.class, WolframClass::Periodic);
+        assert_eq!((frozen.growth, frozen.activity), (1.0, 0.0));
+        assert!(frozen.entropy > 0.0);
+    }
+
+    #[test]
+    fn seeded_sweep() {
+        let config = SweepConfig {
+            generations: 20,
+            trials: 1,
+            ..config(RuleFamily::LifeLike, Some(2))
+        };
+        let summary = |result: SweepResult| {
+            result
+                .rules
+                .iter()
+                .map(|r| {
+                    (
+                        r.rule.clone(),
+                        r.class,
+                        r.activity,
+                        r.growth,
+                        r.entropy,
+                        r.period,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary(sweep(&config).unwrap()),
+            summary(sweep(&config).unwrap())
+        );
+    }
+}
@@ -0,0 +1,227 @@
+pub mod gif;
+pub mod png;
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    palette::{Palette, Rgb, MAX_STATES},
+    sim::Simulation,
+    space::{MAX_COORD, MIN_COORD},
+};
+
+use self::gif::GifEncoder;
+
+const GRID_COLOR: Rgb = Rgb(0x58, 0x6e, 0x75);
+// Cells of room left around the pattern when the region is picked automatically
+const MARGIN: i64 = 2;
+
+// Cells to render as (x_min, x_max, y_min, y_max), inclusive and not wrapped
+pub type Region = (i64, i64, i64, i64);
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    pub region: Region,
+    // Side of a cell in pixels
+    pub scale: usize,
+    // Draw a line between cells, only for rules with fewer than 256 states
+    pub grid: bool,
+}
+
+// Picture with an index into a color table per pixel
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+// Colors of the states of the simulation, followed by the grid color
+pub fn color_table(palette: &Palette, states: usize) -> Vec<Rgb> {
+    let mut colors = (0..states)
+        .map(|cell| palette.color(cell as u8))
+        .collect::<Vec<_>>();
+    if states < MAX_STATES {
+        colors.push(GRID_COLOR);
+    }
+    colors
+}
+
+// Image of the region, with the color indices of `color_table`
+pub fn render(sim: &Simulation, options: &RenderOptions) -> Image {
+    let states = sim.get_cell_types_count();
+    let grid = options.grid && states < MAX_STATES;
+    let (x_min, x_max, y_min, y_max) = options.region;
+    // With a grid, each cell is preceded by a line and one more closes the region
+    let pitch = options.scale + grid as usize;
+    let width = (x_max - x_min + 1) as usize * pitch + grid as usize;
+    let height = (y_max - y_min + 1) as usize * pitch + grid as usize;
+    let mut pixels = vec![states as u8; width * height];
+    for y in y_min..=y_max {
+        for x in x_min..=x_max {
+            let cell = sim.get(x, y).0;
+            let left = (x - x_min) as usize * pitch + grid as usize;
+            let top = (y - y_min) as usize * pitch + grid as usize;
+            for row in top..top + options.scale {
+                pixels[row * width + left..row * width + left + options.scale].fill(cell);
+            }
+        }
+    }
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+// Where the frames of an export go
+#[derive(Clone, Debug)]
+pub enum Output {
+    // Single PNG image of the first frame
+    Image(PathBuf),
+    // Animated GIF
+    Animation(PathBuf),
+    // Numbered PNG images, `<stem>-0000.png` and so on next to the path
+    Sequence(PathBuf),
+}
+
+pub struct ExportConfig {
+    // Generations to run before the first frame
+    pub skip: u64,
+    pub frames: u64,
+    // Generations between frames
+    pub every: u64,
+    // Region to render, the cells visited by the pattern if not set
+    pub region: Option<Region>,
+    pub scale: usize,
+    pub grid: bool,
+    // Time between the frames of an animation
+    pub delay_ms: u64,
+}
+
+fn advance(sim: &mut Simulation, generations: u64) {
+    for _ in 0..generations {
+        sim.step();
+    }
+}
+
+// Union of the bounding boxes of the first frames to export, with a margin
+fn frames_region(sim: &Simulation, config: &ExportConfig, frames: u64) -> Region {
+    let mut sim = sim.clone();
+    advance(&mut sim, config.skip);
+    let mut bounds: Option<Region> = None;
+    for frame in 0..frames {
+        if frame > 0 {
+            advance(&mut sim, config.every);
+        }
+        if let Some((x_min, x_max, y_min, y_max)) = sim.bounding_box() {
+            bounds = Some(match bounds {
+                None => (x_min, x_max, y_min, y_max),
+                Some(b) => (
+                    b.0.min(x_min),
+                    b.1.max(x_max),
+                    b.2.min(y_min),
+                    b.3.max(y_max),
+                ),
+            });
+        }
+    }
+    let Some((x_min, x_max, y_min, y_max)) = bounds else {
+        return (MIN_COORD, MAX_COORD, MIN_COORD, MAX_COORD);
+    };
+    (
+        (x_min - MARGIN).max(MIN_COORD),
+        (x_max + MARGIN).min(MAX_COORD),
+        (y_min - MARGIN).max(MIN_COORD),
+        (y_max + MARGIN).min(MAX_COORD),
+    )
+}
+
+fn sequence_path(path: &Path, frame: u64) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}-{:04}.png", stem, frame))
+}
+
+// Run the simulation and write its frames to the output
+pub fn export(
+    sim: &mut Simulation,
+    palette: &Palette,
+    config: &ExportConfig,
+    output: &Output,
+) -> Result<(), String> {
+    let region = config.region.unwrap_or_else(|| {
+        // A single image only shows the first frame
+        let frames = match output {
+            Output::Image(_) => 1,
+            Output::Animation(_) | Output::Sequence(_) => config.frames,
+        };
+        frames_region(sim, config, frames)
+    });
+    let options = RenderOptions {
+        region,
+        scale: config.scale,
+        grid: config.grid,
+    };
+    let colors = color_table(palette, sim.get_cell_types_count());
+    advance(sim, config.skip);
+
+    match output {
+        Output::Image(path) => png::save(path, &render(sim, &options), &colors),
+        Output::Sequence(path) => {
+            for frame in 0..config.frames {
+                if frame > 0 {
+                    advance(sim, config.every);
+                }
+                png::save(&sequence_path(path, frame), &render(sim, &options), &colors)?;
+            }
+            Ok(())
+        }
+        Output::Animation(path) => {
+            let write_error =
+                |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+            let delay = (config.delay_ms / 10).min(u16::MAX as u64) as u16;
+            let first = render(sim, &options);
+            let file = File::create(path).map_err(write_error)?;
+            let mut encoder =
+                GifEncoder::new(BufWriter::new(file), first.width, first.height, &colors)
+                    .map_err(write_error)?;
+            encoder.add_frame(&first, delay).map_err(write_error)?;
+            for _ in 1..config.frames {
+                advance(sim, config.every);
+                encoder
+                    .add_frame(&render(sim, &options), delay)
+                    .map_err(write_error)?;
+            }
+            encoder.finish().map_err(write_error)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{create_ruleset, Cell};
+
+    #[test]
+    fn region_of_frames() {
+        let mut sim = Simulation::new(create_ruleset("life").unwrap());
+        // Glider moving down and to the right by a cell every 4 generations
+        for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            sim.set(x, y, Cell(1));
+        }
+        let config = ExportConfig {
+            skip: 0,
+            frames: 5,
+            every: 4,
+            region: None,
+            scale: 1,
+            grid: false,
+            delay_ms: 100,
+        };
+        assert_eq!(frames_region(&sim, &config, 1), (-2, 4, -2, 4));
+        assert_eq!(frames_region(&sim, &config, 5), (-2, 8, -2, 8));
+    }
+}
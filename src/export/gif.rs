@@ -0,0 +1,279 @@
+use super::*;
+
+use std::{collections::HashMap, io::Write};
+
+// Animated GIF, see https://www.w3.org/Graphics/GIF/spec-gif89a.txt
+
+const MAX_CODES: u16 = 4096;
+
+// Writes the frames one at a time, so that long recordings don't have to be
+// kept in memory
+pub struct GifEncoder<W: Write> {
+    out: W,
+    width: usize,
+    height: usize,
+    // Bits per color index, the color table has `1 << color_bits` entries
+    color_bits: u32,
+}
+
+impl<W: Write> GifEncoder<W> {
+    pub fn new(mut out: W, width: usize, height: usize, colors: &[Rgb]) -> std::io::Result<Self> {
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{}x{} is too large for a GIF", width, height),
+            ));
+        }
+        let color_bits = (1..=8).find(|bits| colors.len() <= 1 << bits).unwrap_or(8);
+
+        out.write_all(b"GIF89a")?;
+        out.write_all(&(width as u16).to_le_bytes())?;
+        out.write_all(&(height as u16).to_le_bytes())?;
+        // Global color table with 8 bits per channel, background color 0,
+        // square pixels
+        out.write_all(&[0xf0 | (color_bits - 1) as u8, 0, 0])?;
+        for idx in 0..1 << color_bits {
+            let Rgb(r, g, b) = colors.get(idx).copied().unwrap_or(Rgb(0, 0, 0));
+            out.write_all(&[r, g, b])?;
+        }
+        // Loop forever
+        out.write_all(&[0x21, 0xff, 0x0b])?;
+        out.write_all(b"NETSCAPE2.0")?;
+        out.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+
+        Ok(GifEncoder {
+            out,
+            width,
+            height,
+            color_bits,
+        })
+    }
+
+    // Add a frame of the size of the animation, shown for `delay` hundredths of a second
+    pub fn add_frame(&mut self, image: &Image, delay: u16) -> std::io::Result<()> {
+        if image.width != self.width || image.height != self.height {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "frames must all have the same size",
+            ));
+        }
+        // Graphic control extension with the delay, and no transparency
+        self.out.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        self.out.write_all(&delay.to_le_bytes())?;
+        self.out.write_all(&[0x00, 0x00])?;
+        // Image descriptor covering the whole animation, with the global colors
+        self.out.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.out.write_all(&(self.width as u16).to_le_bytes())?;
+        self.out.write_all(&(self.height as u16).to_le_bytes())?;
+        self.out.write_all(&[0x00])?;
+
+        let min_code_size = self.color_bits.max(2);
+        self.out.write_all(&[min_code_size as u8])?;
+        let data = lzw(&image.pixels, min_code_size);
+        for block in data.chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0x00])
+    }
+
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.out.write_all(&[0x3b])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+// Variable length codes packed starting from the least significant bit
+struct CodeWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.bits |= (code as u32) << self.count;
+        self.count += size;
+        while self.count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+fn lzw(pixels: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear_code = 1u16 << min_code_size;
+    let end_code = clear_code + 1;
+    let mut out = CodeWriter {
+        bytes: vec![],
+        bits: 0,
+        count: 0,
+    };
+    // Code of each sequence of pixels, as the code of its prefix and its last pixel
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end_code + 1;
+    out.write(clear_code, code_size);
+
+    let mut pixels = pixels.iter();
+    if let Some(&first) = pixels.next() {
+        let mut current = first as u16;
+        for &pixel in pixels {
+            if let Some(&code) = codes.get(&(current, pixel)) {
+                current = code;
+                continue;
+            }
+            out.write(current, code_size);
+            if next_code == MAX_CODES {
+                // The table is full, start over
+                out.write(clear_code, code_size);
+                codes.clear();
+                code_size = min_code_size + 1;
+                next_code = end_code + 1;
+            } else {
+                if next_code >= 1 << code_size {
+                    code_size += 1;
+                }
+                codes.insert((current, pixel), next_code);
+                next_code += 1;
+            }
+            current = pixel as u16;
+        }
+        out.write(current, code_size);
+    }
+    out.write(end_code, code_size);
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Decode the codes written by lzw(), along with the number of clear codes
+    fn decode(data: &[u8], min_code_size: u32) -> (Vec<u8>, usize) {
+        let clear_code = 1usize << min_code_size;
+        let end_code = clear_code + 1;
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut code_size = min_code_size + 1;
+        let mut previous: Option<usize> = None;
+        let mut pos = 0;
+        let mut clears = 0;
+        let mut pixels = vec![];
+        loop {
+            let mut code = 0;
+            for i in 0..code_size as usize {
+                let bit = (data[(pos + i) / 8] >> ((pos + i) % 8)) & 1;
+                code |= (bit as usize) << i;
+            }
+            pos += code_size as usize;
+
+            if code == clear_code {
+                table = (0..clear_code).map(|pixel| vec![pixel as u8]).collect();
+                // Clear and end codes
+                table.extend([vec![], vec![]]);
+                code_size = min_code_size + 1;
+                previous = None;
+                clears += 1;
+                continue;
+            }
+            if code == end_code {
+                break;
+            }
+            let sequence = match previous {
+                _ if code < table.len() => table[code].clone(),
+                // The sequence being added, starting with its own first pixel
+                Some(previous) if code == table.len() => {
+                    let mut sequence = table[previous].clone();
+                    sequence.push(sequence[0]);
+                    sequence
+                }
+                _ => panic!("invalid code {}", code),
+            };
+            if let Some(previous) = previous {
+                if table.len() < MAX_CODES as usize {
+                    let mut added = table[previous].clone();
+                    added.push(sequence[0]);
+                    table.push(added);
+                }
+            }
+            pixels.extend(&sequence);
+            previous = Some(code);
+            if table.len() == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+        }
+        assert_eq!(pos.div_ceil(8), data.len());
+        (pixels, clears)
+    }
+
+    fn pseudo_random_pixels(count: usize, colors: u32) -> Vec<u8> {
+        let mut seed = 1u32;
+        (0..count)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                ((seed >> 16) % colors) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        for (pixels, min_code_size) in [
+            (vec![], 2),
+            (vec![3], 2),
+            (vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1], 2),
+            (vec![0; 100_000], 2),
+            ((0..=255).collect(), 8),
+            (pseudo_random_pixels(5000, 4), 2),
+        ] {
+            assert_eq!(
+                decode(&lzw(&pixels, min_code_size), min_code_size).0,
+                pixels
+            );
+        }
+    }
+
+    #[test]
+    fn lzw_table_reset() {
+        // Barely compressible pixels fill up the table many times over
+        let pixels = pseudo_random_pixels(100_000, 8);
+        let (decoded, clears) = decode(&lzw(&pixels, 3), 3);
+        assert_eq!(decoded, pixels);
+        assert!(clears > 2);
+
+        let pixels = pseudo_random_pixels(200_000, 256);
+        let (decoded, clears) = decode(&lzw(&pixels, 8), 8);
+        assert_eq!(decoded, pixels);
+        assert!(clears > 2);
+    }
+
+    #[test]
+    fn frame_size() {
+        let colors = [Rgb(0, 0, 0), Rgb(255, 255, 255)];
+        let mut encoder = GifEncoder::new(vec![], 2, 2, &colors).unwrap();
+        let frame = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![0, 1, 1, 0],
+        };
+        encoder.add_frame(&frame, 10).unwrap();
+        let other = Image {
+            width: 1,
+            height: 4,
+            pixels: vec![0, 1, 1, 0],
+        };
+        assert!(encoder.add_frame(&other, 10).is_err());
+        let gif = encoder.finish().unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(gif.last(), Some(&0x3b));
+    }
+}
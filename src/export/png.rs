@@ -0,0 +1,393 @@
+use super::*;
+
+// Indexed color PNG, see https://www.w3.org/TR/png/
+// The image data is compressed with fixed Huffman codes, which is much
+// simpler than building per-image codes and enough for flat colored cells.
+
+const SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const WINDOW_SIZE: usize = 1 << 15;
+const HASH_BITS: u32 = 15;
+// Previous positions tried when looking for a match
+const MAX_CHAIN: usize = 64;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+pub fn save(path: &Path, image: &Image, colors: &[Rgb]) -> Result<(), String> {
+    std::fs::write(path, encode(image, colors))
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+pub fn encode(image: &Image, colors: &[Rgb]) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+
+    let mut header = vec![];
+    header.extend((image.width as u32).to_be_bytes());
+    header.extend((image.height as u32).to_be_bytes());
+    // 8 bits per pixel, indexed color, default compression, filter and no interlacing
+    header.extend([8, 3, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+
+    let palette: Vec<u8> = colors
+        .iter()
+        .flat_map(|Rgb(r, g, b)| [*r, *g, *b])
+        .collect();
+    write_chunk(&mut png, b"PLTE", &palette);
+
+    // Each row starts with its filter type, none
+    let mut data = Vec::with_capacity((image.width + 1) * image.height);
+    for row in image.pixels.chunks(image.width.max(1)) {
+        data.push(0);
+        data.extend_from_slice(row);
+    }
+    write_chunk(&mut png, b"IDAT", &zlib(&data));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // Sums of this many bytes can't overflow before the modulo
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+// Writes bits starting from the least significant bit of each byte
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            bytes: vec![],
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, count: u32) {
+        self.bits |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are stored starting from their most significant bit
+    fn write_code(&mut self, code: u32, count: u32) {
+        self.write(code.reverse_bits() >> (32 - count), count);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+// Fixed Huffman code of a literal, a length or the end of the block
+fn write_symbol(out: &mut BitWriter, symbol: u16) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => out.write_code(0x30 + symbol, 8),
+        144..=255 => out.write_code(0x190 + symbol - 144, 9),
+        256..=279 => out.write_code(symbol - 256, 7),
+        _ => out.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(out: &mut BitWriter, length: usize, distance: usize) {
+    let idx = LENGTH_BASES
+        .iter()
+        .rposition(|base| *base as usize <= length)
+        .unwrap();
+    write_symbol(out, 257 + idx as u16);
+    out.write(
+        (length - LENGTH_BASES[idx] as usize) as u32,
+        LENGTH_EXTRA_BITS[idx] as u32,
+    );
+    let idx = DISTANCE_BASES
+        .iter()
+        .rposition(|base| *base as usize <= distance)
+        .unwrap();
+    out.write_code(idx as u32, 5);
+    out.write(
+        (distance - DISTANCE_BASES[idx] as usize) as u32,
+        DISTANCE_EXTRA_BITS[idx] as u32,
+    );
+}
+
+fn hash(data: &[u8]) -> usize {
+    let value = (data[0] as u32) << 16 | (data[1] as u32) << 8 | data[2] as u32;
+    (value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+// Compress the data into a single deflate block, finding repeated bytes
+// through chains of earlier positions with the same hash
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::new();
+    // Last block, fixed Huffman codes
+    out.write(1, 1);
+    out.write(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash(&data[pos..]);
+            prev[pos % WINDOW_SIZE] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let max_length = (data.len() - pos).min(MAX_MATCH);
+        let mut best = (0, 0);
+        if max_length >= MIN_MATCH {
+            let mut candidate = head[hash(&data[pos..])];
+            for _ in 0..MAX_CHAIN {
+                if candidate == usize::MAX || pos - candidate > WINDOW_SIZE - 1 {
+                    break;
+                }
+                let length = data[candidate..]
+                    .iter()
+                    .zip(&data[pos..pos + max_length])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best.0 {
+                    best = (length, pos - candidate);
+                    if length == max_length {
+                        break;
+                    }
+                }
+                let next = prev[candidate % WINDOW_SIZE];
+                // Older entries of the ring may have been overwritten by newer positions
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+            }
+        }
+
+        let (length, distance) = best;
+        if length >= MIN_MATCH {
+            write_match(&mut out, length, distance);
+            for p in pos..pos + length {
+                insert(p, &mut head, &mut prev);
+            }
+            pos += length;
+        } else {
+            write_symbol(&mut out, data[pos] as u16);
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+    write_symbol(&mut out, 256);
+    out.finish()
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32K window, no dictionary
+    let mut out = vec![0x78, 0x01];
+    out.extend(deflate(data));
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads bits starting from the least significant bit of each byte
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn read(&mut self, count: u32) -> usize {
+            let mut value = 0;
+            for i in 0..count {
+                let bit = (self.bytes[self.pos / 8] >> (self.pos % 8)) & 1;
+                value |= (bit as usize) << i;
+                self.pos += 1;
+            }
+            value
+        }
+
+        fn read_code(&mut self, count: u32) -> usize {
+            (0..count).fold(0, |code, _| code << 1 | self.read(1))
+        }
+
+        // Fixed Huffman literal, length or end of block symbol
+        fn read_symbol(&mut self) -> usize {
+            let code = self.read_code(7);
+            if code <= 0x17 {
+                return 256 + code;
+            }
+            let code = code << 1 | self.read(1);
+            match code {
+                0x30..=0xbf => code - 0x30,
+                0xc0..=0xc7 => 280 + code - 0xc0,
+                _ => 144 + (code << 1 | self.read(1)) - 0x190,
+            }
+        }
+    }
+
+    // Decompress the single fixed Huffman block written by zlib()
+    fn inflate(zlib: &[u8]) -> Vec<u8> {
+        assert_eq!((zlib[0] as u16) << 8 | zlib[1] as u16, 0x7801);
+        let mut reader = BitReader {
+            bytes: &zlib[2..zlib.len() - 4],
+            pos: 0,
+        };
+        assert_eq!(reader.read(1), 1);
+        assert_eq!(reader.read(2), 1);
+        let mut data: Vec<u8> = vec![];
+        loop {
+            match reader.read_symbol() {
+                literal @ 0..=255 => data.push(literal as u8),
+                256 => break,
+                symbol => {
+                    let idx = symbol - 257;
+                    let length =
+                        LENGTH_BASES[idx] as usize + reader.read(LENGTH_EXTRA_BITS[idx] as u32);
+                    let idx = reader.read_code(5);
+                    let distance =
+                        DISTANCE_BASES[idx] as usize + reader.read(DISTANCE_EXTRA_BITS[idx] as u32);
+                    assert!(distance <= data.len() && distance < WINDOW_SIZE);
+                    for _ in 0..length {
+                        data.push(data[data.len() - distance]);
+                    }
+                }
+            }
+        }
+        assert_eq!(reader.pos.div_ceil(8), reader.bytes.len());
+        let checksum = u32::from_be_bytes(zlib[zlib.len() - 4..].try_into().unwrap());
+        assert_eq!(checksum, adler32(&data));
+        data
+    }
+
+    fn pseudo_random_bytes(count: usize, modulo: u32) -> Vec<u8> {
+        let mut seed = 1u32;
+        (0..count)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                ((seed >> 16) % modulo) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(&[0xff; 100_000]), {
+            let (a, b) = (0..100_000u64).fold((1u64, 0u64), |(a, b), _| {
+                let a = (a + 0xff) % 65521;
+                (a, (b + a) % 65521)
+            });
+            (b << 16 | a) as u32
+        });
+    }
+
+    #[test]
+    fn zlib_round_trip() {
+        let mut mixed = vec![0; 1000];
+        mixed.extend(pseudo_random_bytes(50_000, 256));
+        mixed.extend(b"abcabcabcabd".repeat(3000));
+        mixed.extend(pseudo_random_bytes(50_000, 3));
+        for data in [
+            vec![],
+            b"a".to_vec(),
+            b"Hello, hello, hello!".to_vec(),
+            vec![7; 100_000],
+            pseudo_random_bytes(100_000, 256),
+            mixed,
+        ] {
+            assert_eq!(inflate(&zlib(&data)), data);
+        }
+    }
+
+    #[test]
+    fn png_chunks() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![0, 1, 2, 2, 1, 0],
+        };
+        let png = encode(&image, &[Rgb(0, 0, 0), Rgb(255, 0, 0), Rgb(0, 0, 255)]);
+        assert_eq!(&png[..8], SIGNATURE);
+        let mut pos = 8;
+        let mut kinds = vec![];
+        while pos < png.len() {
+            let length = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let chunk = &png[pos + 4..pos + 8 + length];
+            let crc =
+                u32::from_be_bytes(png[pos + 8 + length..pos + 12 + length].try_into().unwrap());
+            assert_eq!(crc, crc32(chunk));
+            let (kind, data) = chunk.split_at(4);
+            if kind == b"IDAT" {
+                assert_eq!(inflate(data), [0, 0, 1, 2, 0, 2, 1, 0]);
+            }
+            kinds.push(String::from_utf8_lossy(kind).into_owned());
+            pos += 12 + length;
+        }
+        assert_eq!(kinds, ["IHDR", "PLTE", "IDAT", "IEND"]);
+    }
+}
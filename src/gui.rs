@@ -1,40 +1,376 @@
+pub mod camera;
+pub mod editor;
+pub mod files;
+pub mod input;
+pub mod lod;
+pub mod overlay;
+pub mod panel;
+pub mod record;
+pub mod speed;
+pub mod tools;
+pub mod undo;
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
 use raylib::prelude::*;
 
+use self::{
+    camera::Camera,
+    editor::{Editor, Selection},
+    files::{DialogEvent, DialogMode, FileDialog, RecentFiles},
+    input::{InputEvent, TextInput},
+    lod::SpaceTexture,
+    overlay::{Overlay, OverlayTexture},
+    panel::{Panel, PanelAction},
+    record::Recording,
+    speed::Autostep,
+    tools::{Library, Tool, Tools},
+    undo::UndoStack,
+};
 use crate::{
-    rules::{Cell, RuleSet},
+    export::RenderOptions,
+    palette::{Palette, Rgb},
+    pattern::Pattern,
+    periodicity::{PeriodDetector, Periodicity},
+    rules::Cell,
     sim::Simulation,
+    space::{wrap, MAX_COORD, MIN_COORD},
+    stats::GenerationStats,
 };
 
-struct GuiState {
+pub struct GuiState {
     sim: Simulation,
     current_cell: Cell,
+    detector: PeriodDetector,
+    periodicity: Option<Periodicity>,
+    undo: UndoStack,
+    palette: Palette,
+    // Where palettes are loaded from when the ruleset changes
+    palette_path: Option<PathBuf>,
+}
+
+// What text typed into the prompt is for
+enum Prompt {
+    RunUntil,
+}
+
+// Command line options of the GUI
+pub struct GuiOptions {
+    // Directory of patterns for the stamp tool
+    pub library: Option<PathBuf>,
+    // Palette file, or directory of palettes named after the rulesets
+    pub palette: Option<PathBuf>,
 }
 
 impl GuiState {
-    pub fn new(ruleset: RuleSet) -> Self {
+    pub fn new(sim: Simulation, palette_path: Option<PathBuf>) -> Self {
+        let mut detector = PeriodDetector::new(true);
+        detector.observe(&sim);
+        let palette = load_palette(palette_path.as_deref(), &sim);
         GuiState {
-            sim: Simulation::new(ruleset),
+            sim,
             current_cell: Cell(0),
+            detector,
+            periodicity: None,
+            undo: UndoStack::new(),
+            palette,
+            palette_path,
+        }
+    }
+
+    pub fn step(&mut self) {
+        self.sim.step();
+        if self.periodicity.is_none() {
+            self.periodicity = self.detector.observe(&self.sim);
+        }
+    }
+
+    // Edits break the cycle, start looking for a new one
+    pub fn edited(&mut self) {
+        self.detector.reset();
+        self.detector.observe(&self.sim);
+        self.periodicity = None;
+    }
+
+    // Apply an edit that can be undone
+    pub fn edit(&mut self, f: impl FnOnce(&mut Simulation)) {
+        let hash = self.sim.hash();
+        self.undo.checkpoint(&self.sim);
+        f(&mut self.sim);
+        if self.sim.hash() == hash {
+            self.undo.discard();
+        } else {
+            self.undo.commit();
+            self.edited();
+        }
+    }
+
+    // Switch rules, keeping the cells the new ruleset has states for
+    pub fn set_ruleset(&mut self, name: &str) {
+        let Some(ruleset) = crate::rules::create_ruleset(name) else {
+            warn!("Unknown ruleset {}", name);
+            return;
+        };
+        self.sim.set_ruleset(ruleset);
+        self.palette = load_palette(self.palette_path.as_deref(), &self.sim);
+        if self.current_cell.0 as usize >= self.sim.get_cell_types_count() {
+            self.current_cell = Cell(0);
+        }
+        self.undo.clear();
+        self.edited();
+    }
+}
+
+// Color of a cell state, to draw with
+fn cell_color(palette: &Palette, cell: u8) -> Color {
+    let Rgb(r, g, b) = palette.color(cell);
+    Color::new(r, g, b, 255)
+}
+
+// Colors for the ruleset of the simulation, the default ones if there
+// is no palette for it
+fn load_palette(path: Option<&Path>, sim: &Simulation) -> Palette {
+    let Some(path) = path else {
+        return Palette::new();
+    };
+    Palette::load_for_ruleset(path, sim.ruleset_name(), sim.get_cell_types_count()).unwrap_or_else(
+        |e| {
+            warn!("{}", e);
+            Palette::new()
+        },
+    )
+}
+
+// Generations shown in the population plot, and kept in the history
+const PLOT_GENERATIONS: usize = 200;
+
+// Population of each non-zero state over the most recent generations
+fn draw_population_plot(
+    d: &mut RaylibDrawHandle,
+    history: &VecDeque<GenerationStats>,
+    palette: &Palette,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) {
+    d.draw_rectangle(x, y, width, height, Color::from_hex("073642").unwrap());
+    d.draw_rectangle_lines(x, y, width, height, Color::from_hex("586e75").unwrap());
+
+    let max_population = history
+        .iter()
+        .flat_map(|stats| stats.population.iter().skip(1))
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let point = |idx: usize, population: usize| {
+        Vector2::new(
+            x as f32 + idx as f32 * width as f32 / PLOT_GENERATIONS as f32,
+            (y + height) as f32 - population as f32 * height as f32 / max_population as f32,
+        )
+    };
+    for (idx, (prev, curr)) in history.iter().zip(history.iter().skip(1)).enumerate() {
+        for state in 1..curr.population.len() {
+            d.draw_line_v(
+                point(idx, prev.population[state]),
+                point(idx + 1, curr.population[state]),
+                cell_color(palette, state as u8),
+            );
+        }
+    }
+}
+
+// Listed over the viewport with <H>
+const COMMANDS: &[&str] = &[
+    "Step: <Space>",
+    "Auto step: <Shift+Space>",
+    "Speed: <S> <D>, gens/tick: <G>",
+    "Run until step: <U>",
+    "Pick cell: <0..9>, <Tab>, <Shift+Tab>",
+    "Draw: <Left>, erase: <Right>",
+    "Tool: <T>, <Shift+T>",
+    "Stamp: <[> <]>, add selection: <K>",
+    "Undo: <Ctrl+Z>, redo: <Ctrl+Y>",
+    "Randomize view or selection: <R>",
+    "Select: <Shift+drag>, all: <Ctrl+A>",
+    "Copy, cut, paste: <Ctrl+C/X/V>",
+    "Paste mode: <P>",
+    "Rotate: <,> <.>, flip: <X> <Y>",
+    "Fill selection: <Ctrl+F>",
+    "Clear selection: <Delete>",
+    "Deselect, cancel paste: <Esc>",
+    "Zoom: <Wheel>, <+>, <->",
+    "Pan: <Middle drag>, <Arrows>",
+    "Fit to pattern: <F>",
+    "Zoomed-out shading: <L>",
+    "Activity overlay: <O>",
+    "Open pattern: <Ctrl+O>",
+    "Save universe, selection: <Ctrl+S>, <Ctrl+Shift+S>",
+    "Record GIF of the view: <Ctrl+R>",
+    "Save PNG of the view: <Ctrl+P>",
+];
+
+// List of the commands over the viewport
+fn draw_help(d: &mut impl RaylibDraw, viewport: Rectangle) {
+    let width = 300;
+    let height = 12 * (COMMANDS.len() as i32 + 2);
+    let (x, y) = (viewport.x as i32 + 8, viewport.y as i32 + 8);
+    d.draw_rectangle(
+        x,
+        y,
+        width,
+        height,
+        Color::from_hex("002b36").unwrap().fade(0.9),
+    );
+    d.draw_rectangle_lines(x, y, width, height, Color::from_hex("586e75").unwrap());
+    d.draw_text(
+        "Commands:",
+        x + 6,
+        y + 6,
+        12,
+        Color::from_hex("eee8d5").unwrap(),
+    );
+    for (idx, line) in COMMANDS.iter().enumerate() {
+        d.draw_text(
+            &format!("  - {}", line),
+            x + 6,
+            y + 6 + 12 * (idx as i32 + 1),
+            12,
+            Color::from_hex("839496").unwrap(),
+        );
+    }
+}
+
+// Grid lines are hidden when cells are smaller than this
+const MIN_GRID_CELL_SIZE: f32 = 6.0;
+
+// Image export of the visible cells, looking like the view
+fn view_render_options(camera: &Camera) -> RenderOptions {
+    RenderOptions {
+        region: camera.visible_cells(),
+        scale: camera.cell_size.round().clamp(1.0, 8.0) as usize,
+        grid: camera.cell_size >= MIN_GRID_CELL_SIZE,
+    }
+}
+
+// Start or stop recording, returning the status to show
+fn toggle_recording(
+    recording: &mut Option<Recording>,
+    state: &GuiState,
+    options: RenderOptions,
+    now: f64,
+) -> String {
+    let result = match recording.take() {
+        Some(current) => current
+            .stop(now)
+            .map(|path| format!("Saved {}", path.display())),
+        None => Recording::start(&state.sim, &state.palette, options, now).map(|started| {
+            let status = format!("Recording {}", started.path.display());
+            *recording = Some(started);
+            status
+        }),
+    };
+    result.unwrap_or_else(|e| {
+        warn!("{}", e);
+        "Failed to record".to_owned()
+    })
+}
+
+// Random cells in the selection, or in the visible cells without one
+fn randomize(state: &mut GuiState, editor: &Editor, camera: &Camera) {
+    if let Some(selection) = editor.selection {
+        state.edit(|sim| selection.randomize(sim));
+    } else {
+        let (x_min, x_max, y_min, y_max) = camera.visible_cells();
+        state.edit(|sim| sim.randomize(x_min, x_max + 1, y_min, y_max + 1));
+    }
+}
+
+// Empty the selection, or the whole space without one
+fn clear(state: &mut GuiState, editor: &Editor) {
+    match editor.selection {
+        Some(selection) => state.edit(|sim| selection.fill(sim, Cell(0))),
+        None => state.edit(|sim| sim.clear()),
+    }
+}
+
+// Selection outline and the pattern being pasted, following the mouse
+fn draw_editor(
+    d: &mut impl RaylibDraw,
+    camera: &Camera,
+    editor: &Editor,
+    palette: &Palette,
+    mouse_x: i64,
+    mouse_y: i64,
+) {
+    let cell_size = Vector2::new(camera.cell_size, camera.cell_size);
+    if let Some(floating) = &editor.floating {
+        let (x0, y0) = editor::centered_at(floating, mouse_x, mouse_y);
+        for y in 0..floating.height() {
+            for x in 0..floating.width() {
+                let cell = floating.get(x, y);
+                if cell.0 != 0 {
+                    d.draw_rectangle_v(
+                        camera.to_screen(x0 + x, y0 + y),
+                        cell_size,
+                        cell_color(palette, cell.0).fade(0.6),
+                    );
+                }
+            }
         }
+        let top_left = camera.to_screen(x0, y0);
+        let bottom_right = camera.to_screen(x0 + floating.width(), y0 + floating.height());
+        d.draw_rectangle_lines_ex(
+            Rectangle::new(
+                top_left.x,
+                top_left.y,
+                bottom_right.x - top_left.x,
+                bottom_right.y - top_left.y,
+            ),
+            1,
+            Color::from_hex("b58900").unwrap(),
+        );
+    }
+    if let Some(selection) = editor.selection {
+        let top_left = camera.to_screen(selection.x_min, selection.y_min);
+        let bottom_right = camera.to_screen(selection.x_max + 1, selection.y_max + 1);
+        let rect = Rectangle::new(
+            top_left.x,
+            top_left.y,
+            bottom_right.x - top_left.x,
+            bottom_right.y - top_left.y,
+        );
+        d.draw_rectangle_rec(rect, Color::from_hex("eee8d5").unwrap().fade(0.15));
+        d.draw_rectangle_lines_ex(rect, 1, Color::from_hex("eee8d5").unwrap());
     }
 }
 
-fn palette(cell_idx: u8) -> Color {
-    match cell_idx {
-        0 => Color::from_hex("073642").unwrap(),
-        1 => Color::from_hex("268bd2").unwrap(),
-        2 => Color::from_hex("2aa198").unwrap(),
-        3 => Color::from_hex("859900").unwrap(),
-        4 => Color::from_hex("6c71c4").unwrap(),
-        5 => Color::from_hex("d33682").unwrap(),
-        6 => Color::from_hex("b58900").unwrap(),
-        7 => Color::from_hex("cb4b16").unwrap(),
-        8 => Color::from_hex("dc322f").unwrap(),
-        _ => panic!("Unsupported cell type count"),
+// Cells the drawing tool would draw, under the mouse or along the drag
+fn draw_tools(
+    d: &mut impl RaylibDraw,
+    camera: &Camera,
+    tools: &Tools,
+    palette: &Palette,
+    mouse_x: i64,
+    mouse_y: i64,
+    current_cell: Cell,
+) {
+    let cell_size = Vector2::new(camera.cell_size, camera.cell_size);
+    for (x, y, cell) in tools.preview(mouse_x, mouse_y, current_cell) {
+        d.draw_rectangle_v(
+            camera.to_screen(x, y),
+            cell_size,
+            cell_color(palette, cell.0).fade(0.6),
+        );
     }
 }
 
-pub fn gui_main(ruleset: RuleSet, set_up: impl FnOnce(&mut Simulation)) {
+pub fn gui_main(sim: Simulation, options: GuiOptions) {
     logging::set_trace_log(TraceLogLevel::LOG_WARNING);
 
     const WIDTH: i32 = 1024;
@@ -47,163 +383,591 @@ pub fn gui_main(ruleset: RuleSet, set_up: impl FnOnce(&mut Simulation)) {
         .title(&format!("Cellariella v{}", env!("CARGO_PKG_VERSION")))
         .build();
 
-    let mut state = GuiState::new(ruleset);
+    // Escape cancels editing first and only then closes the window
+    rl.set_exit_key(None);
+
+    let mut state = GuiState::new(sim, options.palette);
+    state.sim.record_statistics(PLOT_GENERATIONS);
+    let mut editor = Editor::new();
+    let mut tools = Tools::new(Library::load(options.library));
 
-    set_up(&mut state.sim);
+    let mut camera = Camera::new(Rectangle::new(
+        MARGIN as f32,
+        MARGIN as f32,
+        (WIDTH - RIGHT_SIDE_WIDTH - MARGIN * 2) as f32,
+        HEIGHT as f32,
+    ));
+    let mut space_texture = SpaceTexture::new();
+    let mut overlay_texture = OverlayTexture::new();
+    const ZOOM_STEP: f32 = 1.25;
+    // Pixels per second when panning with the arrow keys
+    const PAN_SPEED: f32 = 600.0;
 
-    const STEP_TIME: f64 = 0.100;
-    let mut time = rl.get_time();
-    let mut autostep = false;
+    let mut panel = Panel::new(WIDTH - RIGHT_SIDE_WIDTH, MARGIN, RIGHT_SIDE_WIDTH - MARGIN);
+    let mut show_help = false;
+    let mut autostep = Autostep::new();
+    let mut prompt: Option<(Prompt, TextInput)> = None;
+    let mut dialog: Option<FileDialog> = None;
+    let mut recent_files = RecentFiles::load();
+    // Outcome of the last file operation or export
+    let mut status: Option<String> = None;
+    let mut recording: Option<Recording> = None;
+    let mut last_mouse_pos = rl.get_mouse_position();
     while !rl.window_should_close() {
         // ===== HIT TEST =====
 
-        const CELL_SIZE: i32 = 12;
-        const FIELD_SIZE: i64 = 32;
-
         let mouse_pos = rl.get_mouse_position();
-        let mouse_pos_space_x =
-            (mouse_pos.x as i64 - MARGIN as i64) / CELL_SIZE as i64 - FIELD_SIZE;
-        let mouse_pos_space_y =
-            (mouse_pos.y as i64 - MARGIN as i64) / CELL_SIZE as i64 - FIELD_SIZE;
+        let mouse_in_view = camera.contains(mouse_pos) && dialog.is_none();
+        let (mouse_pos_space_x, mouse_pos_space_y) = camera.cell_at(mouse_pos);
 
         // ===== INTERACTION =====
 
-        if let Some(k) = rl.get_key_pressed() {
+        let shift = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
+            || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+        let ctrl = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+            || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+
+        // Keys go to the prompt or the file dialog while they are open
+        let typing = prompt.is_some() || dialog.is_some();
+        match prompt.as_mut().map(|(_, input)| input.update(&mut rl)) {
+            Some(InputEvent::Done(text)) => match prompt.take().map(|(kind, _)| kind) {
+                Some(Prompt::RunUntil) => match text.trim().parse::<u64>() {
+                    Ok(step) => autostep.run_until(step, state.sim.current_step(), rl.get_time()),
+                    Err(_) => warn!("Invalid step: {:?}", text),
+                },
+                None => {}
+            },
+            Some(InputEvent::Cancelled) => prompt = None,
+            Some(InputEvent::Editing) | None => {}
+        }
+        match dialog
+            .as_mut()
+            .map(|dialog| dialog.update(&mut rl, &recent_files, camera.viewport))
+        {
+            Some(DialogEvent::Chosen(path)) => {
+                let mode = dialog.take().map_or(DialogMode::Open, |dialog| dialog.mode);
+                let result = match mode {
+                    DialogMode::Open => files::open_pattern(&mut state, &path)
+                        .map(|()| camera.fit(state.sim.bounding_box())),
+                    DialogMode::SaveUniverse => {
+                        files::save_pattern(&path, &Pattern::from_universe(&state.sim))
+                    }
+                    DialogMode::SaveSelection => match editor.selection {
+                        Some(selection) => files::save_pattern(&path, &selection.copy(&state.sim)),
+                        None => Err("Nothing is selected".to_owned()),
+                    },
+                };
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                status = Some(match result {
+                    Ok(()) => {
+                        recent_files.add(&path);
+                        match mode {
+                            DialogMode::Open => format!("Opened {}", file_name),
+                            _ => format!("Saved {}", file_name),
+                        }
+                    }
+                    Err(e) => {
+                        warn!("{}", e);
+                        format!("Failed: {}", file_name)
+                    }
+                });
+            }
+            Some(DialogEvent::Cancelled) => dialog = None,
+            Some(DialogEvent::None) | None => {}
+        }
+
+        if !typing && rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) && !editor.cancel() {
+            break;
+        }
+
+        if let Some(k) = rl.get_key_pressed().filter(|_| !typing) {
             match k {
-                KeyboardKey::KEY_ZERO => {
-                    state.current_cell = Cell(0);
+                KeyboardKey::KEY_A if ctrl => {
+                    editor.selection =
+                        state
+                            .sim
+                            .bounding_box()
+                            .map(|(x_min, x_max, y_min, y_max)| Selection {
+                                x_min,
+                                x_max,
+                                y_min,
+                                y_max,
+                            });
                 }
-                KeyboardKey::KEY_ONE => {
-                    state.current_cell = Cell(1);
+                KeyboardKey::KEY_C if ctrl => {
+                    if let Some(selection) = editor.selection {
+                        let pattern = selection.copy(&state.sim);
+                        editor::copy_to_clipboard(&mut rl, &mut editor, pattern);
+                    }
                 }
-                KeyboardKey::KEY_TWO => {
-                    state.current_cell = Cell(2);
+                KeyboardKey::KEY_X if ctrl => {
+                    if let Some(selection) = editor.selection {
+                        let pattern = selection.copy(&state.sim);
+                        editor::copy_to_clipboard(&mut rl, &mut editor, pattern);
+                        state.edit(|sim| selection.fill(sim, Cell(0)));
+                    }
                 }
-                KeyboardKey::KEY_THREE => {
-                    state.current_cell = Cell(3);
+                KeyboardKey::KEY_V if ctrl => {
+                    match editor::paste_from_clipboard(&editor, state.sim.get_cell_types_count()) {
+                        Ok(pattern) => editor.floating = pattern,
+                        Err(e) => status = Some(e),
+                    }
                 }
-                KeyboardKey::KEY_FOUR => {
-                    state.current_cell = Cell(4);
+                KeyboardKey::KEY_F if ctrl => {
+                    if let Some(selection) = editor.selection {
+                        let cell = state.current_cell;
+                        state.edit(|sim| selection.fill(sim, cell));
+                    }
                 }
-                KeyboardKey::KEY_FIVE => {
-                    state.current_cell = Cell(5);
+                KeyboardKey::KEY_DELETE | KeyboardKey::KEY_BACKSPACE => {
+                    if let Some(selection) = editor.selection {
+                        state.edit(|sim| selection.fill(sim, Cell(0)));
+                    }
+                }
+                KeyboardKey::KEY_Z | KeyboardKey::KEY_Y if ctrl => {
+                    let done = if shift || k == KeyboardKey::KEY_Y {
+                        state.undo.redo(&mut state.sim)
+                    } else {
+                        state.undo.undo(&mut state.sim)
+                    };
+                    if done {
+                        state.edited();
+                    }
+                }
+                KeyboardKey::KEY_T => {
+                    tools.tool = if shift {
+                        tools.tool.previous()
+                    } else {
+                        tools.tool.next()
+                    };
+                }
+                KeyboardKey::KEY_LEFT_BRACKET => {
+                    tools.library.previous();
+                }
+                KeyboardKey::KEY_RIGHT_BRACKET => {
+                    tools.library.next();
+                }
+                KeyboardKey::KEY_K => {
+                    if let Some(selection) = editor.selection {
+                        tools.library.add(selection.copy(&state.sim));
+                        tools.tool = Tool::Stamp;
+                    }
+                }
+                KeyboardKey::KEY_P if ctrl => {
+                    let options = view_render_options(&camera);
+                    status = Some(
+                        match record::snapshot(&state.sim, &state.palette, &options) {
+                            Ok(path) => format!("Saved {}", path.display()),
+                            Err(e) => {
+                                warn!("{}", e);
+                                "Failed to save the image".to_owned()
+                            }
+                        },
+                    );
                 }
-                KeyboardKey::KEY_SIX => {
-                    state.current_cell = Cell(6);
+                KeyboardKey::KEY_R if ctrl => {
+                    let options = view_render_options(&camera);
+                    status = Some(toggle_recording(
+                        &mut recording,
+                        &state,
+                        options,
+                        rl.get_time(),
+                    ));
                 }
-                KeyboardKey::KEY_SEVEN => {
-                    state.current_cell = Cell(7);
+                KeyboardKey::KEY_P => {
+                    editor.paste_mode = editor.paste_mode.next();
                 }
-                KeyboardKey::KEY_EIGHT => {
-                    state.current_cell = Cell(8);
+                KeyboardKey::KEY_PERIOD
+                | KeyboardKey::KEY_COMMA
+                | KeyboardKey::KEY_X
+                | KeyboardKey::KEY_Y => {
+                    let transform = match k {
+                        KeyboardKey::KEY_PERIOD => Pattern::rotated,
+                        KeyboardKey::KEY_COMMA => editor::rotated_counterclockwise,
+                        KeyboardKey::KEY_X => Pattern::flipped,
+                        _ => editor::flipped_vertically,
+                    };
+                    if let Some(floating) = &editor.floating {
+                        editor.floating = Some(transform(floating));
+                    } else if let Some(selection) = editor.selection {
+                        let mut transformed = selection;
+                        state.edit(|sim| transformed = selection.transform(sim, transform));
+                        editor.selection = Some(transformed);
+                    } else if tools.tool == Tool::Stamp {
+                        tools.library.transform_current(transform);
+                    }
+                }
+                KeyboardKey::KEY_ZERO
+                | KeyboardKey::KEY_ONE
+                | KeyboardKey::KEY_TWO
+                | KeyboardKey::KEY_THREE
+                | KeyboardKey::KEY_FOUR
+                | KeyboardKey::KEY_FIVE
+                | KeyboardKey::KEY_SIX
+                | KeyboardKey::KEY_SEVEN
+                | KeyboardKey::KEY_EIGHT
+                | KeyboardKey::KEY_NINE => {
+                    let state_idx = k as usize - KeyboardKey::KEY_ZERO as usize;
+                    if state_idx < state.sim.get_cell_types_count() {
+                        state.current_cell = Cell(state_idx as u8);
+                    }
                 }
-                KeyboardKey::KEY_NINE => {
-                    state.current_cell = Cell(9);
+                KeyboardKey::KEY_TAB => {
+                    let states = state.sim.get_cell_types_count();
+                    let offset = if shift { states - 1 } else { 1 };
+                    state.current_cell =
+                        Cell(((state.current_cell.0 as usize + offset) % states) as u8);
                 }
                 KeyboardKey::KEY_SPACE => {
-                    if autostep {
-                        autostep = false;
+                    if autostep.running {
+                        autostep.stop();
                     } else {
-                        if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) {
-                            autostep = true;
-                            time = rl.get_time();
+                        if shift {
+                            autostep.start(rl.get_time());
                         }
-                        state.sim.step();
+                        state.step();
+                    }
+                }
+                KeyboardKey::KEY_D => {
+                    autostep.faster();
+                }
+                KeyboardKey::KEY_O if ctrl => {
+                    dialog = Some(FileDialog::new(DialogMode::Open, &recent_files));
+                }
+                KeyboardKey::KEY_S if ctrl => {
+                    let mode = if shift && editor.selection.is_some() {
+                        DialogMode::SaveSelection
+                    } else {
+                        DialogMode::SaveUniverse
+                    };
+                    dialog = Some(FileDialog::new(mode, &recent_files));
+                }
+                KeyboardKey::KEY_S => {
+                    autostep.slower();
+                }
+                KeyboardKey::KEY_G => {
+                    if shift {
+                        autostep.fewer_generations();
+                    } else {
+                        autostep.more_generations();
                     }
                 }
+                KeyboardKey::KEY_U => {
+                    let input = TextInput::new("Run until step:", "", |c| c.is_ascii_digit());
+                    prompt = Some((Prompt::RunUntil, input));
+                }
                 KeyboardKey::KEY_R => {
-                    state
-                        .sim
-                        .randomize(-FIELD_SIZE, FIELD_SIZE, -FIELD_SIZE, FIELD_SIZE);
+                    randomize(&mut state, &editor, &camera);
+                }
+                KeyboardKey::KEY_H => {
+                    show_help = !show_help;
+                }
+                KeyboardKey::KEY_L => {
+                    space_texture.shading = space_texture.shading.toggled();
+                }
+                KeyboardKey::KEY_O => {
+                    overlay_texture.overlay = overlay_texture.overlay.next();
+                    // Activity is only tracked while it is shown
+                    if overlay_texture.overlay == Overlay::None {
+                        state.sim.stop_recording_activity();
+                    } else if state.sim.activity().is_none() {
+                        state.sim.record_activity();
+                    }
+                }
+                KeyboardKey::KEY_F => {
+                    camera.fit(state.sim.bounding_box());
+                }
+                KeyboardKey::KEY_EQUAL | KeyboardKey::KEY_KP_ADD => {
+                    let center = camera.viewport_center();
+                    camera.zoom(center, ZOOM_STEP);
+                }
+                KeyboardKey::KEY_MINUS | KeyboardKey::KEY_KP_SUBTRACT => {
+                    let center = camera.viewport_center();
+                    camera.zoom(center, 1.0 / ZOOM_STEP);
                 }
                 _ => {}
             }
         }
 
-        if rl.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON) {
-            state
-                .sim
-                .set(mouse_pos_space_x, mouse_pos_space_y, state.current_cell);
-        } else if rl.is_mouse_button_down(MouseButton::MOUSE_RIGHT_BUTTON) {
-            state.sim.set(mouse_pos_space_x, mouse_pos_space_y, Cell(0));
+        if dialog.is_none() && rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            match panel.click(mouse_pos, state.sim.get_cell_types_count()) {
+                Some(PanelAction::Step) => state.step(),
+                Some(PanelAction::Run) => autostep.start(rl.get_time()),
+                Some(PanelAction::Pause) => autostep.stop(),
+                Some(PanelAction::Randomize) => randomize(&mut state, &editor, &camera),
+                Some(PanelAction::Clear) => clear(&mut state, &editor),
+                Some(PanelAction::Record) => {
+                    let options = view_render_options(&camera);
+                    status = Some(toggle_recording(
+                        &mut recording,
+                        &state,
+                        options,
+                        rl.get_time(),
+                    ));
+                }
+                Some(PanelAction::PickCell(cell)) => state.current_cell = cell,
+                Some(PanelAction::PickRuleset(name)) => state.set_ruleset(name),
+                None => {}
+            }
         }
 
-        let wheel_move = rl.get_mouse_wheel_move() as i32;
-        if wheel_move != 0 {}
+        if let Some(floating) = &editor.floating {
+            // Placed when the button is released so that it doesn't start painting
+            if mouse_in_view && rl.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON) {
+                let (x0, y0) = editor::centered_at(floating, mouse_pos_space_x, mouse_pos_space_y);
+                let mode = editor.paste_mode;
+                state.edit(|sim| editor::paste(sim, floating, x0, y0, mode));
+                editor.floating = None;
+            } else if rl.is_mouse_button_pressed(MouseButton::MOUSE_RIGHT_BUTTON) {
+                editor.floating = None;
+            }
+        } else if editor.is_selecting() {
+            if rl.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON) {
+                editor.drag_selection(mouse_pos_space_x, mouse_pos_space_y);
+            } else {
+                editor.end_selection();
+            }
+        } else if mouse_in_view
+            && shift
+            && rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON)
+        {
+            editor.start_selection(mouse_pos_space_x, mouse_pos_space_y);
+        } else if tools.is_dragging() {
+            if rl.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON)
+                || rl.is_mouse_button_down(MouseButton::MOUSE_RIGHT_BUTTON)
+            {
+                tools.drag(&mut state, mouse_pos_space_x, mouse_pos_space_y);
+            } else {
+                tools.release(&mut state);
+            }
+        } else if mouse_in_view && rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            let cell = state.current_cell;
+            tools.press(
+                &mut state,
+                mouse_pos_space_x,
+                mouse_pos_space_y,
+                cell,
+                editor.paste_mode,
+            );
+        } else if mouse_in_view
+            && tools.tool != Tool::Stamp
+            && rl.is_mouse_button_pressed(MouseButton::MOUSE_RIGHT_BUTTON)
+        {
+            // The right button draws with empty cells
+            tools.press(
+                &mut state,
+                mouse_pos_space_x,
+                mouse_pos_space_y,
+                Cell(0),
+                editor.paste_mode,
+            );
+        }
+
+        if rl.is_mouse_button_down(MouseButton::MOUSE_MIDDLE_BUTTON) {
+            camera.pan(Vector2::new(
+                mouse_pos.x - last_mouse_pos.x,
+                mouse_pos.y - last_mouse_pos.y,
+            ));
+        }
+        last_mouse_pos = mouse_pos;
+
+        let pan = PAN_SPEED * rl.get_frame_time();
+        for (key, delta) in [
+            (KeyboardKey::KEY_LEFT, Vector2::new(pan, 0.0)),
+            (KeyboardKey::KEY_RIGHT, Vector2::new(-pan, 0.0)),
+            (KeyboardKey::KEY_UP, Vector2::new(0.0, pan)),
+            (KeyboardKey::KEY_DOWN, Vector2::new(0.0, -pan)),
+        ] {
+            if !typing && rl.is_key_down(key) {
+                camera.pan(delta);
+            }
+        }
+
+        let wheel_move = rl.get_mouse_wheel_move();
+        if wheel_move != 0.0 && mouse_in_view {
+            camera.zoom(mouse_pos, ZOOM_STEP.powf(wheel_move));
+        }
 
         // ===== HANDLING =====
 
-        if autostep {
-            let curr_time = rl.get_time();
-            if curr_time - time >= STEP_TIME {
-                time = curr_time;
-                state.sim.step();
+        autostep.update(&mut state, rl.get_time());
+        if let Some(current) = &mut recording {
+            if let Err(e) = current.update(&state.sim, rl.get_time()) {
+                warn!("{}", e);
+                recording = None;
+                status = Some("Failed to record".to_owned());
             }
         }
 
         // ===== DRAWING =====
 
+        space_texture.update(
+            &mut rl,
+            &thread,
+            &state.sim,
+            &state.palette,
+            camera.cell_size,
+        );
+        overlay_texture.update(&mut rl, &thread, &state.sim);
+
         let mut d = rl.begin_drawing(&thread);
         d.clear_background(Color::from_hex("002b36").unwrap());
 
-        for x in -FIELD_SIZE..FIELD_SIZE {
-            for y in -FIELD_SIZE..FIELD_SIZE {
-                let cell = state.sim.get(x, y);
-                let color = palette(cell.0);
-                d.draw_rectangle(
-                    MARGIN + (x + FIELD_SIZE) as i32 * CELL_SIZE,
-                    MARGIN + (y + FIELD_SIZE) as i32 * CELL_SIZE,
-                    CELL_SIZE,
-                    CELL_SIZE,
-                    color,
+        {
+            let viewport = camera.viewport;
+            let mut d = d.begin_scissor_mode(
+                viewport.x as i32,
+                viewport.y as i32,
+                viewport.width as i32,
+                viewport.height as i32,
+            );
+            space_texture.draw(&mut d, &camera);
+            overlay_texture.draw(&mut d, &camera);
+            draw_editor(
+                &mut d,
+                &camera,
+                &editor,
+                &state.palette,
+                mouse_pos_space_x,
+                mouse_pos_space_y,
+            );
+            if (mouse_in_view || tools.is_dragging())
+                && editor.floating.is_none()
+                && !editor.is_selecting()
+            {
+                draw_tools(
+                    &mut d,
+                    &camera,
+                    &tools,
+                    &state.palette,
+                    mouse_pos_space_x,
+                    mouse_pos_space_y,
+                    state.current_cell,
                 );
             }
+
+            if camera.cell_size >= MIN_GRID_CELL_SIZE {
+                let (x_min, x_max, y_min, y_max) = camera.visible_cells();
+                let top_left = camera.to_screen(x_min, y_min);
+                let bottom_right = camera.to_screen(x_max + 1, y_max + 1);
+                for x in x_min..=x_max + 1 {
+                    let line_x = camera.to_screen(x, y_min).x;
+                    d.draw_line_v(
+                        Vector2::new(line_x, top_left.y),
+                        Vector2::new(line_x, bottom_right.y),
+                        Color::from_hex("586e75").unwrap(),
+                    );
+                }
+                for y in y_min..=y_max + 1 {
+                    let line_y = camera.to_screen(x_min, y).y;
+                    d.draw_line_v(
+                        Vector2::new(top_left.x, line_y),
+                        Vector2::new(bottom_right.x, line_y),
+                        Color::from_hex("586e75").unwrap(),
+                    );
+                }
+            }
         }
-        for x in -FIELD_SIZE..=FIELD_SIZE {
-            d.draw_line(
-                MARGIN + (x + FIELD_SIZE) as i32 * CELL_SIZE,
-                MARGIN,
-                MARGIN + (x + FIELD_SIZE) as i32 * CELL_SIZE,
-                MARGIN + 2 * FIELD_SIZE as i32 * CELL_SIZE,
-                Color::from_hex("586e75").unwrap(),
-            );
-        }
-        for y in -FIELD_SIZE..=FIELD_SIZE {
-            d.draw_line(
-                MARGIN,
-                MARGIN + (y + FIELD_SIZE) as i32 * CELL_SIZE,
-                MARGIN + 2 * FIELD_SIZE as i32 * CELL_SIZE,
-                MARGIN + (y + FIELD_SIZE) as i32 * CELL_SIZE,
-                Color::from_hex("586e75").unwrap(),
+
+        if let Some((_, input)) = &prompt {
+            let viewport = camera.viewport;
+            input.draw(
+                &mut d,
+                viewport.x as i32,
+                (viewport.y + viewport.height) as i32 - 20,
+                viewport.width as i32,
             );
         }
 
         // Right side
 
-        let lines = &[
-            "Commands:".to_owned(),
-            "  - Step: <Space>".to_owned(),
-            "  - Auto step: <Shift+Space>".to_owned(),
-            "  - Pick cell: <0..9>".to_owned(),
-            "  - Randomize all: <R>".to_owned(),
+        let mut lines = vec![
+            "Help: <H>".to_owned(),
             format!("Current simulation step: {}", state.sim.current_step()),
+            format!(
+                "Speed: {} ({:.1} gens/s)",
+                autostep,
+                autostep.generations_per_second()
+            ),
+            format!("Zoom: {:.2} px/cell", camera.cell_size),
+            format!("Paste mode: {}", editor.paste_mode),
+            format!("Tool: {}", tools.tool),
+            format!("Overlay: {}", overlay_texture.overlay),
             "Logical mouse coords:".to_owned(),
-            format!("  - x: {}", mouse_pos_space_x),
-            format!("  - y: {}", mouse_pos_space_y),
+            format!("  - x: {}", wrap(mouse_pos_space_x)),
+            format!("  - y: {}", wrap(mouse_pos_space_y)),
             format!(
                 "  - Cell: {}",
                 state.sim.get(mouse_pos_space_x, mouse_pos_space_y).0
             ),
         ];
+        if let Some(stats) = state.sim.history().back() {
+            lines.push(format!(
+                "Births: {}, deaths: {}",
+                stats.births, stats.deaths
+            ));
+            lines.push(format!("Bounding box: {}x{}", stats.width, stats.height));
+        }
+        if let Some(status) = &status {
+            lines.push(status.clone());
+        }
+        if let Some(recording) = &recording {
+            lines.push(format!("Recording: {} frames", recording.frames));
+        }
+        if let Some(until) = autostep.until {
+            lines.push(format!("Running until step {}", until));
+        }
+        if let Some(periodicity) = state.periodicity {
+            lines.push(format!("State: {}", periodicity));
+        }
+        if tools.tool == Tool::Stamp {
+            lines.push(format!(
+                "Stamp: {}",
+                tools.library.current_name().unwrap_or("none")
+            ));
+        }
 
+        let lines_y = MARGIN + panel.height(state.sim.get_cell_types_count()) + 12;
         for (idx, line) in lines.iter().enumerate() {
             d.draw_text(
                 line,
                 WIDTH - RIGHT_SIDE_WIDTH,
-                MARGIN + 12 * idx as i32,
+                lines_y + 12 * idx as i32,
                 12,
                 Color::from_hex("839496").unwrap(),
             );
         }
+
+        draw_population_plot(
+            &mut d,
+            state.sim.history(),
+            &state.palette,
+            WIDTH - RIGHT_SIDE_WIDTH,
+            lines_y + 12 * (lines.len() as i32 + 1),
+            RIGHT_SIDE_WIDTH - MARGIN,
+            120,
+        );
+
+        panel.draw(
+            &mut d,
+            &state,
+            autostep.running,
+            recording.is_some(),
+            mouse_pos,
+        );
+
+        if show_help {
+            draw_help(&mut d, camera.viewport);
+        }
+        if let Some(dialog) = &dialog {
+            dialog.draw(&mut d, camera.viewport);
+        }
+    }
+
+    // Don't leave a truncated GIF behind
+    if let Some(recording) = recording {
+        if let Err(e) = recording.stop(rl.get_time()) {
+            warn!("{}", e);
+        }
     }
 }
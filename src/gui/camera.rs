@@ -0,0 +1,201 @@
+use super::*;
+
+use crate::space::{MAX_COORD, MIN_COORD};
+
+const SIDE: f32 = (MAX_COORD - MIN_COORD + 1) as f32;
+const MAX_CELL_SIZE: f32 = 64.0;
+// Zoomed all the way out the whole space is 32 pixels across
+const MIN_CELL_SIZE: f32 = 1.0 / 8.0;
+// Cell size used when there is nothing to fit to
+const DEFAULT_CELL_SIZE: f32 = 12.0;
+
+// Maps space coordinates to a rectangle of the window and back
+pub struct Camera {
+    // Area of the window the space is drawn in
+    pub viewport: Rectangle,
+    // Space coordinates shown in the middle of the viewport
+    pub center: Vector2,
+    // Side of a cell in pixels
+    pub cell_size: f32,
+}
+
+impl Camera {
+    pub fn new(viewport: Rectangle) -> Self {
+        Camera {
+            viewport,
+            center: Vector2::zero(),
+            cell_size: DEFAULT_CELL_SIZE,
+        }
+    }
+
+    // Whether the point is over a visible cell
+    pub fn contains(&self, pos: Vector2) -> bool {
+        let (x, y) = self.cell_at(pos);
+        let (x_min, x_max, y_min, y_max) = self.visible_cells();
+        self.viewport.check_collision_point_rec(pos)
+            && (x_min..=x_max).contains(&x)
+            && (y_min..=y_max).contains(&y)
+    }
+
+    pub fn viewport_center(&self) -> Vector2 {
+        Vector2::new(
+            self.viewport.x + self.viewport.width / 2.0,
+            self.viewport.y + self.viewport.height / 2.0,
+        )
+    }
+
+    // Space coordinates (in cells, fractional) of a point of the window
+    pub fn to_space(&self, pos: Vector2) -> Vector2 {
+        let center = self.viewport_center();
+        Vector2::new(
+            self.center.x + (pos.x - center.x) / self.cell_size,
+            self.center.y + (pos.y - center.y) / self.cell_size,
+        )
+    }
+
+    // Cell under a point of the window, not wrapped
+    pub fn cell_at(&self, pos: Vector2) -> (i64, i64) {
+        let pos = self.to_space(pos);
+        (pos.x.floor() as i64, pos.y.floor() as i64)
+    }
+
+    // Top left corner of a cell in the window
+    pub fn to_screen(&self, x: i64, y: i64) -> Vector2 {
+        let center = self.viewport_center();
+        Vector2::new(
+            center.x + (x as f32 - self.center.x) * self.cell_size,
+            center.y + (y as f32 - self.center.y) * self.cell_size,
+        )
+    }
+
+    // Cells at least partially visible as (x_min, x_max, y_min, y_max),
+    // inclusive and not wrapped. The space is shown once, centered,
+    // rather than repeated when it is smaller than the viewport.
+    pub fn visible_cells(&self) -> (i64, i64, i64, i64) {
+        let top_left = self.cell_at(Vector2::new(self.viewport.x, self.viewport.y));
+        let bottom_right = self.cell_at(Vector2::new(
+            self.viewport.x + self.viewport.width,
+            self.viewport.y + self.viewport.height,
+        ));
+        let side = SIDE as i64;
+        let x_from = (self.center.x - SIDE / 2.0).round() as i64;
+        let y_from = (self.center.y - SIDE / 2.0).round() as i64;
+        (
+            top_left.0.max(x_from),
+            bottom_right.0.min(x_from + side - 1),
+            top_left.1.max(y_from),
+            bottom_right.1.min(y_from + side - 1),
+        )
+    }
+
+    // Change the cell size by the factor, keeping the cell under `pos` in place
+    pub fn zoom(&mut self, pos: Vector2, factor: f32) {
+        let anchor = self.to_space(pos);
+        self.cell_size = (self.cell_size * factor).clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+        let moved = self.to_space(pos);
+        self.center.x += anchor.x - moved.x;
+        self.center.y += anchor.y - moved.y;
+        self.wrap_center();
+    }
+
+    // Move the contents of the viewport by the number of pixels
+    pub fn pan(&mut self, delta: Vector2) {
+        self.center.x -= delta.x / self.cell_size;
+        self.center.y -= delta.y / self.cell_size;
+        self.wrap_center();
+    }
+
+    // Center the bounds (x_min, x_max, y_min, y_max) with as large cells as fit
+    pub fn fit(&mut self, bounds: Option<(i64, i64, i64, i64)>) {
+        let Some((x_min, x_max, y_min, y_max)) = bounds else {
+            self.center = Vector2::zero();
+            self.cell_size = DEFAULT_CELL_SIZE;
+            return;
+        };
+        // Leave a cell of room on every side
+        let width = (x_max - x_min + 3) as f32;
+        let height = (y_max - y_min + 3) as f32;
+        self.center = Vector2::new(
+            (x_min + x_max + 1) as f32 / 2.0,
+            (y_min + y_max + 1) as f32 / 2.0,
+        );
+        self.cell_size = (self.viewport.width / width)
+            .min(self.viewport.height / height)
+            .clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+    }
+
+    // The space wraps around, keep the center in its range
+    fn wrap_center(&mut self) {
+        let wrap = |v: f32| (v - MIN_COORD as f32).rem_euclid(SIDE) + MIN_COORD as f32;
+        self.center = Vector2::new(wrap(self.center.x), wrap(self.center.y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(Rectangle::new(10.0, 20.0, 200.0, 100.0));
+        camera.cell_size = 10.0;
+        camera
+    }
+
+    #[test]
+    fn screen_to_cell() {
+        let camera = camera();
+        assert_eq!(camera.viewport_center(), Vector2::new(110.0, 70.0));
+        assert_eq!(camera.cell_at(Vector2::new(110.0, 70.0)), (0, 0));
+        assert_eq!(camera.cell_at(Vector2::new(109.0, 69.0)), (-1, -1));
+        assert_eq!(camera.to_screen(0, 0), Vector2::new(110.0, 70.0));
+        for (x, y) in [(0, 0), (-10, 4), (7, -3)] {
+            let corner = camera.to_screen(x, y);
+            let inside = Vector2::new(corner.x + 5.0, corner.y + 9.0);
+            assert_eq!(camera.cell_at(inside), (x, y));
+        }
+        assert_eq!(camera.visible_cells(), (-10, 10, -5, 5));
+    }
+
+    #[test]
+    fn zoom_keeps_anchor() {
+        let mut camera = camera();
+        let anchor = Vector2::new(150.0, 30.0);
+        let before = camera.to_space(anchor);
+        camera.zoom(anchor, 2.0);
+        assert_eq!(camera.cell_size, 20.0);
+        let after = camera.to_space(anchor);
+        assert!((before.x - after.x).abs() < 1e-4 && (before.y - after.y).abs() < 1e-4);
+
+        camera.zoom(anchor, 1000.0);
+        assert_eq!(camera.cell_size, MAX_CELL_SIZE);
+        camera.zoom(anchor, 0.0);
+        assert_eq!(camera.cell_size, MIN_CELL_SIZE);
+        // The whole space fits, and is shown only once
+        let (x_min, x_max, y_min, y_max) = camera.visible_cells();
+        assert_eq!(
+            (x_max - x_min + 1, y_max - y_min + 1),
+            (SIDE as i64, SIDE as i64)
+        );
+    }
+
+    #[test]
+    fn pan_wraps() {
+        let mut camera = camera();
+        camera.pan(Vector2::new(-10.0, 30.0));
+        assert_eq!(camera.center, Vector2::new(1.0, -3.0));
+        // Half the space to the left and a bit more wraps around
+        camera.pan(Vector2::new(1300.0, 0.0));
+        assert_eq!(camera.center.x, SIDE - 129.0);
+    }
+
+    #[test]
+    fn fit_bounds() {
+        let mut camera = camera();
+        camera.fit(Some((0, 9, 0, 4)));
+        assert_eq!(camera.center, Vector2::new(5.0, 2.5));
+        assert_eq!(camera.cell_size, 100.0 / 7.0);
+        camera.fit(None);
+        assert_eq!(camera.center, Vector2::zero());
+        assert_eq!(camera.cell_size, DEFAULT_CELL_SIZE);
+    }
+}
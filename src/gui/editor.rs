@@ -0,0 +1,344 @@
+use super::*;
+
+use log::warn;
+
+use crate::pattern::rle;
+
+// How pasted cells combine with the cells already there
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PasteMode {
+    Overwrite,
+    // Empty pasted cells keep the cells below
+    Or,
+    // Pasted cells clear cells of the same state and replace the others
+    Xor,
+}
+
+impl PasteMode {
+    pub fn next(self) -> Self {
+        match self {
+            PasteMode::Overwrite => PasteMode::Or,
+            PasteMode::Or => PasteMode::Xor,
+            PasteMode::Xor => PasteMode::Overwrite,
+        }
+    }
+
+    fn combine(self, old: Cell, new: Cell) -> Cell {
+        match self {
+            PasteMode::Overwrite => new,
+            PasteMode::Or | PasteMode::Xor if new.0 == 0 => old,
+            PasteMode::Or => new,
+            PasteMode::Xor if old == new => Cell(0),
+            PasteMode::Xor => new,
+        }
+    }
+}
+
+impl std::fmt::Display for PasteMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasteMode::Overwrite => write!(f, "overwrite"),
+            PasteMode::Or => write!(f, "OR"),
+            PasteMode::Xor => write!(f, "XOR"),
+        }
+    }
+}
+
+// Rectangle of cells, inclusive and not wrapped
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Selection {
+    pub x_min: i64,
+    pub x_max: i64,
+    pub y_min: i64,
+    pub y_max: i64,
+}
+
+impl Selection {
+    pub fn from_corners(a: (i64, i64), b: (i64, i64)) -> Self {
+        Selection {
+            x_min: a.0.min(b.0),
+            x_max: a.0.max(b.0),
+            y_min: a.1.min(b.1),
+            y_max: a.1.max(b.1),
+        }
+    }
+
+    pub fn width(&self) -> i64 {
+        self.x_max - self.x_min + 1
+    }
+
+    pub fn height(&self) -> i64 {
+        self.y_max - self.y_min + 1
+    }
+
+    // All cells of the selection, empty ones included
+    pub fn copy(&self, sim: &Simulation) -> Pattern {
+        let mut pattern = Pattern::new(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                pattern.set(x, y, sim.get(self.x_min + x, self.y_min + y));
+            }
+        }
+        pattern.rule = crate::rules::pattern_rule_name(sim.ruleset_name());
+        pattern
+    }
+
+    pub fn fill(&self, sim: &mut Simulation, cell: Cell) {
+        for y in self.y_min..=self.y_max {
+            for x in self.x_min..=self.x_max {
+                sim.set(x, y, cell);
+            }
+        }
+    }
+
+    pub fn randomize(&self, sim: &mut Simulation) {
+        sim.randomize(self.x_min, self.x_max + 1, self.y_min, self.y_max + 1);
+    }
+
+    // Replace the contents with a transformed copy anchored at the top left
+    // corner, returning the selection around the result
+    pub fn transform(&self, sim: &mut Simulation, f: impl Fn(&Pattern) -> Pattern) -> Selection {
+        let pattern = f(&self.copy(sim));
+        self.fill(sim, Cell(0));
+        paste(sim, &pattern, self.x_min, self.y_min, PasteMode::Overwrite);
+        Selection {
+            x_min: self.x_min,
+            x_max: self.x_min + pattern.width() - 1,
+            y_min: self.y_min,
+            y_max: self.y_min + pattern.height() - 1,
+        }
+    }
+}
+
+// Put the pattern into the simulation with its top left corner at (x0, y0)
+pub fn paste(sim: &mut Simulation, pattern: &Pattern, x0: i64, y0: i64, mode: PasteMode) {
+    for y in 0..pattern.height() {
+        for x in 0..pattern.width() {
+            let old = sim.get(x0 + x, y0 + y);
+            sim.set(x0 + x, y0 + y, mode.combine(old, pattern.get(x, y)));
+        }
+    }
+}
+
+// Top left corner of the pattern when centered on the cell
+pub fn centered_at(pattern: &Pattern, x: i64, y: i64) -> (i64, i64) {
+    (x - pattern.width() / 2, y - pattern.height() / 2)
+}
+
+// Rotated by 90 degrees counterclockwise
+pub fn rotated_counterclockwise(pattern: &Pattern) -> Pattern {
+    pattern.rotated().rotated().rotated()
+}
+
+// Mirrored top to bottom
+pub fn flipped_vertically(pattern: &Pattern) -> Pattern {
+    pattern.flipped().rotated().rotated()
+}
+
+pub struct Editor {
+    pub selection: Option<Selection>,
+    // Cell the selection is being dragged from
+    selecting_from: Option<(i64, i64)>,
+    // Last copied cells, used when the system clipboard has no other pattern
+    pub clipboard: Option<Pattern>,
+    pub paste_mode: PasteMode,
+    // Pattern following the mouse until it is placed
+    pub floating: Option<Pattern>,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Editor {
+            selection: None,
+            selecting_from: None,
+            clipboard: None,
+            paste_mode: PasteMode::Overwrite,
+            floating: None,
+        }
+    }
+
+    pub fn is_selecting(&self) -> bool {
+        self.selecting_from.is_some()
+    }
+
+    pub fn start_selection(&mut self, x: i64, y: i64) {
+        self.selecting_from = Some((x, y));
+        self.selection = Some(Selection::from_corners((x, y), (x, y)));
+    }
+
+    pub fn drag_selection(&mut self, x: i64, y: i64) {
+        if let Some(from) = self.selecting_from {
+            self.selection = Some(Selection::from_corners(from, (x, y)));
+        }
+    }
+
+    pub fn end_selection(&mut self) {
+        self.selecting_from = None;
+    }
+
+    // Leave whatever is in progress, returns false if there was nothing
+    pub fn cancel(&mut self) -> bool {
+        if self.floating.take().is_some() {
+            return true;
+        }
+        self.selecting_from = None;
+        self.selection.take().is_some()
+    }
+}
+
+// Copy the pattern as RLE text to the system clipboard as well
+pub fn copy_to_clipboard(rl: &mut RaylibHandle, editor: &mut Editor, pattern: Pattern) {
+    if let Err(e) = rl.set_clipboard_text(&crate::pattern::rle::write(&pattern)) {
+        warn!("Failed to copy to the clipboard: {}", e);
+    }
+    editor.clipboard = Some(pattern);
+}
+
+// The RLE pattern on the system clipboard, or the last copied cells. Those
+// are used as they are while the clipboard still holds them as copied,
+// keeping the empty cells around them.
+fn clipboard_pattern(text: Option<&str>, copied: Option<&Pattern>) -> Option<Pattern> {
+    let Some(text) = text else {
+        return copied.cloned();
+    };
+    if copied.is_some_and(|pattern| rle::write(pattern) == text) {
+        return copied.cloned();
+    }
+    // Text copied from elsewhere is only taken for a pattern with a header
+    if !rle::has_header(text) {
+        return copied.cloned();
+    }
+    rle::parse(text)
+        .ok()
+        .filter(|pattern| pattern.width() > 0 && pattern.height() > 0)
+        .or_else(|| copied.cloned())
+}
+
+// The pattern to paste, as long as the ruleset has all its states
+pub fn paste_from_clipboard(editor: &Editor, states: usize) -> Result<Option<Pattern>, String> {
+    // RaylibHandle::get_clipboard_text() doesn't handle an empty clipboard
+    // SAFETY: raylib returns either null or a NUL-terminated string it owns,
+    // which stays valid until the next clipboard call and is copied right away
+    let text = unsafe {
+        let text = raylib::ffi::GetClipboardText();
+        (!text.is_null()).then(|| {
+            std::ffi::CStr::from_ptr(text)
+                .to_string_lossy()
+                .into_owned()
+        })
+    };
+    match clipboard_pattern(text.as_deref(), editor.clipboard.as_ref()) {
+        Some(pattern) if !pattern.fits_states(states) => {
+            Err("The pasted pattern has states the ruleset doesn't have".to_owned())
+        }
+        pattern => Ok(pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sim_with(cells: &[(i64, i64)]) -> Simulation {
+        let mut sim = Simulation::new(crate::rules::create_ruleset("life").unwrap());
+        for &(x, y) in cells {
+            sim.set(x, y, Cell(1));
+        }
+        sim
+    }
+
+    fn live_cells(sim: &Simulation) -> Vec<(i64, i64)> {
+        let mut cells = vec![];
+        for y in MIN_COORD..=MAX_COORD {
+            for x in MIN_COORD..=MAX_COORD {
+                if sim.get(x, y).0 != 0 {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn selection_corners() {
+        let selection = Selection::from_corners((5, -1), (2, 3));
+        assert_eq!((selection.x_min, selection.x_max), (2, 5));
+        assert_eq!((selection.y_min, selection.y_max), (-1, 3));
+        assert_eq!((selection.width(), selection.height()), (4, 5));
+
+        let mut editor = Editor::new();
+        editor.start_selection(5, -1);
+        editor.drag_selection(2, 3);
+        assert!(editor.is_selecting());
+        editor.end_selection();
+        assert_eq!(editor.selection, Some(selection));
+        assert!(editor.cancel());
+        assert!(!editor.cancel());
+    }
+
+    #[test]
+    fn rotate_selection() {
+        // Three cells along the top of a 3x2 selection and one below its left end
+        //   OOO      OO
+        //   O..  ->  .O
+        //            .O
+        let mut sim = sim_with(&[(10, 10), (11, 10), (12, 10), (10, 11)]);
+        let selection = Selection::from_corners((10, 10), (12, 11));
+        let rotated = selection.transform(&mut sim, |p| p.rotated());
+        assert_eq!(rotated, Selection::from_corners((10, 10), (11, 12)));
+        assert_eq!(live_cells(&sim), [(10, 10), (11, 10), (11, 11), (11, 12)]);
+
+        // Rotating back the other way restores the cells
+        rotated.transform(&mut sim, rotated_counterclockwise);
+        assert_eq!(live_cells(&sim), [(10, 10), (11, 10), (12, 10), (10, 11)]);
+    }
+
+    #[test]
+    fn flip_selection() {
+        let mut sim = sim_with(&[(0, 0), (1, 0), (2, 1)]);
+        let selection = Selection::from_corners((0, 0), (2, 1));
+        assert_eq!(selection.transform(&mut sim, flipped_vertically), selection);
+        assert_eq!(live_cells(&sim), [(2, 0), (0, 1), (1, 1)]);
+        selection.transform(&mut sim, |p| p.flipped());
+        assert_eq!(live_cells(&sim), [(0, 0), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn clipboard() {
+        let mut copied = Pattern::new(3, 3);
+        copied.set(1, 1, Cell(1));
+        let copied_text = rle::write(&copied);
+        // The copied cells keep their empty border
+        let pasted = clipboard_pattern(Some(&copied_text), Some(&copied)).unwrap();
+        assert_eq!((pasted.width(), pasted.height()), (3, 3));
+        assert_eq!(clipboard_pattern(None, Some(&copied)), Some(copied.clone()));
+
+        let blinker = "#N Blinker\nx = 3, y = 1, rule = B3/S23\n3o!\n";
+        let pasted = clipboard_pattern(Some(blinker), Some(&copied)).unwrap();
+        assert_eq!((pasted.width(), pasted.height()), (3, 1));
+        assert_eq!(clipboard_pattern(Some(blinker), None), Some(pasted));
+
+        // Text without a pattern header is no pattern
+        assert_eq!(clipboard_pattern(Some("hello"), None), None);
+        assert_eq!(
+            clipboard_pattern(Some("hello"), Some(&copied)),
+            Some(copied)
+        );
+        assert_eq!(clipboard_pattern(Some("x = 0, y = 0\n!"), None), None);
+    }
+
+    #[test]
+    fn paste_modes() {
+        let pattern = Pattern::from_cells([(0, 0, Cell(1)), (2, 0, Cell(1))]).unwrap();
+        for (mode, expected) in [
+            (PasteMode::Overwrite, vec![(0, 0), (2, 0)]),
+            (PasteMode::Or, vec![(0, 0), (1, 0), (2, 0)]),
+            (PasteMode::Xor, vec![(1, 0), (2, 0)]),
+        ] {
+            let mut sim = sim_with(&[(0, 0), (1, 0)]);
+            paste(&mut sim, &pattern, 0, 0, mode);
+            assert_eq!(live_cells(&sim), expected, "{}", mode);
+        }
+        assert_eq!(centered_at(&pattern, 10, 10), (9, 10));
+    }
+}
@@ -0,0 +1,415 @@
+use super::*;
+
+use log::warn;
+
+use super::input::{InputEvent, TextInput};
+
+// Files kept in the recent files list
+const MAX_RECENT_FILES: usize = 10;
+const ROW_HEIGHT: i32 = 14;
+const DIALOG_WIDTH: i32 = 420;
+const DIALOG_HEIGHT: i32 = 440;
+// Extensions of the pattern formats, other files are not listed
+const PATTERN_EXTENSIONS: &[&str] = &["rle", "mc", "cells", "lif", "life"];
+
+// Recently opened or saved pattern files, most recent first, kept in
+// the user configuration directory
+pub struct RecentFiles {
+    pub paths: Vec<PathBuf>,
+}
+
+fn recent_files_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("cellariella").join("recent"))
+}
+
+impl RecentFiles {
+    pub fn load() -> Self {
+        let paths = recent_files_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        RecentFiles { paths }
+    }
+
+    pub fn add(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_FILES);
+        if let Err(e) = self.save() {
+            warn!("Failed to save the recent files: {}", e);
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(path) = recent_files_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .paths
+            .iter()
+            .map(|p| format!("{}\n", p.display()))
+            .collect();
+        std::fs::write(path, text)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DialogMode {
+    Open,
+    SaveUniverse,
+    SaveSelection,
+}
+
+impl std::fmt::Display for DialogMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DialogMode::Open => write!(f, "Open pattern"),
+            DialogMode::SaveUniverse => write!(f, "Save universe"),
+            DialogMode::SaveSelection => write!(f, "Save selection"),
+        }
+    }
+}
+
+enum Entry {
+    Recent(PathBuf),
+    Parent,
+    Dir(String),
+    File(String),
+}
+
+pub enum DialogEvent {
+    None,
+    Cancelled,
+    // Path to open or save to, depending on the mode
+    Chosen(PathBuf),
+}
+
+// File browser drawn in the window, listing the recent files and the
+// pattern files of a directory, with a field to type a file name into
+pub struct FileDialog {
+    pub mode: DialogMode,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    // First entry shown
+    scroll: usize,
+    name: TextInput,
+    // Existing file to save to, chosen once Enter is pressed again
+    overwrite: Option<PathBuf>,
+}
+
+impl FileDialog {
+    pub fn new(mode: DialogMode, recent: &RecentFiles) -> Self {
+        // Start where the last pattern was
+        let dir = recent
+            .paths
+            .first()
+            .and_then(|path| path.parent())
+            .filter(|dir| dir.is_dir())
+            .map(Path::to_owned)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        let name = match mode {
+            DialogMode::Open => "",
+            DialogMode::SaveUniverse | DialogMode::SaveSelection => "pattern.rle",
+        };
+        let mut dialog = FileDialog {
+            mode,
+            dir,
+            entries: vec![],
+            selected: 0,
+            scroll: 0,
+            name: TextInput::new("Name:", name, |c| !c.is_control()),
+            overwrite: None,
+        };
+        dialog.list(recent);
+        dialog
+    }
+
+    fn list(&mut self, recent: &RecentFiles) {
+        self.entries.clear();
+        if self.mode == DialogMode::Open {
+            self.entries
+                .extend(recent.paths.iter().cloned().map(Entry::Recent));
+        }
+        if self.dir.parent().is_some() {
+            self.entries.push(Entry::Parent);
+        }
+        let mut dirs = vec![];
+        let mut files = vec![];
+        match std::fs::read_dir(&self.dir) {
+            Ok(read_dir) => {
+                for entry in read_dir.filter_map(|e| e.ok()) {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    if name.starts_with('.') {
+                        continue;
+                    }
+                    let path = entry.path();
+                    if path.is_dir() {
+                        dirs.push(name);
+                    } else if path
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| PATTERN_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+                    {
+                        files.push(name);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to list {}: {}", self.dir.display(), e),
+        }
+        dirs.sort();
+        files.sort();
+        self.entries.extend(dirs.into_iter().map(Entry::Dir));
+        self.entries.extend(files.into_iter().map(Entry::File));
+        self.selected = 0;
+        self.scroll = 0;
+        self.overwrite = None;
+    }
+
+    fn rect(viewport: Rectangle) -> Rectangle {
+        Rectangle::new(
+            viewport.x + (viewport.width - DIALOG_WIDTH as f32) / 2.0,
+            viewport.y + (viewport.height - DIALOG_HEIGHT as f32) / 2.0,
+            DIALOG_WIDTH as f32,
+            DIALOG_HEIGHT as f32,
+        )
+    }
+
+    fn visible_rows() -> usize {
+        ((DIALOG_HEIGHT - 4 * ROW_HEIGHT - 40) / ROW_HEIGHT) as usize
+    }
+
+    // Top of the first row of the list
+    fn list_y(rect: Rectangle) -> f32 {
+        rect.y + 2.0 * ROW_HEIGHT as f32 + 12.0
+    }
+
+    // Go into a directory, pick a file, or just select it when saving
+    fn activate(&mut self, idx: usize, recent: &RecentFiles) -> DialogEvent {
+        let Some(entry) = self.entries.get(idx) else {
+            return DialogEvent::None;
+        };
+        match entry {
+            Entry::Recent(path) => DialogEvent::Chosen(path.clone()),
+            Entry::Parent => {
+                if let Some(parent) = self.dir.parent() {
+                    self.dir = parent.to_owned();
+                }
+                self.list(recent);
+                DialogEvent::None
+            }
+            Entry::Dir(name) => {
+                self.dir = self.dir.join(name);
+                self.list(recent);
+                DialogEvent::None
+            }
+            Entry::File(name) if self.mode == DialogMode::Open => {
+                DialogEvent::Chosen(self.dir.join(name))
+            }
+            Entry::File(name) => {
+                self.name.text = name.clone();
+                DialogEvent::None
+            }
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        rl: &mut RaylibHandle,
+        recent: &RecentFiles,
+        viewport: Rectangle,
+    ) -> DialogEvent {
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        let rows = Self::visible_rows();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+
+        let rect = Self::rect(viewport);
+        let mouse_pos = rl.get_mouse_position();
+        let wheel_move = rl.get_mouse_wheel_move();
+        if wheel_move != 0.0 && rect.check_collision_point_rec(mouse_pos) {
+            let max_scroll = self.entries.len().saturating_sub(rows);
+            self.scroll = (self.scroll as f32 - wheel_move).clamp(0.0, max_scroll as f32) as usize;
+        }
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            let list_y = Self::list_y(rect);
+            let row = ((mouse_pos.y - list_y) / ROW_HEIGHT as f32).floor();
+            let in_list = (rect.x..rect.x + rect.width).contains(&mouse_pos.x)
+                && (0.0..rows as f32).contains(&row);
+            if in_list {
+                let idx = self.scroll + row as usize;
+                if idx < self.entries.len() {
+                    self.selected = idx;
+                    return self.activate(idx, recent);
+                }
+            }
+        }
+
+        match self.name.update(rl) {
+            InputEvent::Cancelled => DialogEvent::Cancelled,
+            // With no name typed, Enter picks the selected entry
+            InputEvent::Done(name) if name.trim().is_empty() => {
+                self.activate(self.selected, recent)
+            }
+            InputEvent::Done(name) => {
+                let path = self.dir.join(name.trim());
+                if path.is_dir() {
+                    self.dir = path;
+                    self.name.text.clear();
+                    self.list(recent);
+                    DialogEvent::None
+                } else if self.mode != DialogMode::Open
+                    && path.exists()
+                    && self.overwrite.as_ref() != Some(&path)
+                {
+                    self.overwrite = Some(path);
+                    DialogEvent::None
+                } else {
+                    DialogEvent::Chosen(path)
+                }
+            }
+            InputEvent::Editing => {
+                // Typing another name takes back the confirmation
+                if self.overwrite.as_ref() != Some(&self.dir.join(self.name.text.trim())) {
+                    self.overwrite = None;
+                }
+                DialogEvent::None
+            }
+        }
+    }
+
+    pub fn draw(&self, d: &mut impl RaylibDraw, viewport: Rectangle) {
+        let rect = Self::rect(viewport);
+        let (x, y) = (rect.x as i32, rect.y as i32);
+        let text_color = Color::from_hex("839496").unwrap();
+        d.draw_rectangle_rec(rect, Color::from_hex("002b36").unwrap());
+        d.draw_rectangle_lines_ex(rect, 1, Color::from_hex("586e75").unwrap());
+        d.draw_text(
+            &self.mode.to_string(),
+            x + 8,
+            y + 8,
+            12,
+            Color::from_hex("eee8d5").unwrap(),
+        );
+        d.draw_text(
+            &self.dir.display().to_string(),
+            x + 8,
+            y + 8 + ROW_HEIGHT,
+            12,
+            text_color,
+        );
+
+        let list_y = Self::list_y(rect) as i32;
+        for (row, (idx, entry)) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(Self::visible_rows())
+            .enumerate()
+        {
+            let row_y = list_y + ROW_HEIGHT * row as i32;
+            if idx == self.selected {
+                d.draw_rectangle(
+                    x + 4,
+                    row_y,
+                    DIALOG_WIDTH - 8,
+                    ROW_HEIGHT,
+                    Color::from_hex("073642").unwrap(),
+                );
+            }
+            let label = match entry {
+                Entry::Recent(path) => format!("* {}", path.display()),
+                Entry::Parent => "../".to_owned(),
+                Entry::Dir(name) => format!("{}/", name),
+                Entry::File(name) => name.clone(),
+            };
+            d.draw_text(&label, x + 8, row_y + 1, 12, text_color);
+        }
+
+        if let Some(path) = &self.overwrite {
+            d.draw_text(
+                &format!(
+                    "{} already exists, <Enter> to overwrite it",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                ),
+                x + 8,
+                y + DIALOG_HEIGHT - 4 * ROW_HEIGHT + 2,
+                12,
+                Color::from_hex("cb4b16").unwrap(),
+            );
+        }
+        self.name.draw(
+            d,
+            x + 4,
+            y + DIALOG_HEIGHT - 3 * ROW_HEIGHT,
+            DIALOG_WIDTH - 8,
+        );
+        d.draw_text(
+            "<Enter> open/save, <Up> <Down> select, <Esc> cancel",
+            x + 8,
+            y + DIALOG_HEIGHT - ROW_HEIGHT - 4,
+            10,
+            text_color,
+        );
+    }
+}
+
+// Replace the universe with the pattern in the file, switching to its rule
+pub fn open_pattern(state: &mut GuiState, path: &Path) -> Result<(), String> {
+    let pattern = crate::pattern::load(path)
+        .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+    let ruleset_name = pattern
+        .rule
+        .as_deref()
+        .and_then(crate::rules::ruleset_from_pattern_rule)
+        .unwrap_or_else(|| state.sim.ruleset_name().to_owned());
+    let Some(ruleset) = crate::rules::create_ruleset(&ruleset_name) else {
+        return Err(format!(
+            "{} needs the ruleset {}, which is unknown",
+            path.display(),
+            ruleset_name
+        ));
+    };
+    if !pattern.fits_states(ruleset.cell_rules.len()) {
+        return Err(format!(
+            "{} has states the ruleset {} doesn't have",
+            path.display(),
+            ruleset_name
+        ));
+    }
+    if ruleset_name != state.sim.ruleset_name() {
+        state.set_ruleset(&ruleset_name);
+    }
+    state.edit(|sim| {
+        sim.clear();
+        pattern.place_centered(sim);
+    });
+    Ok(())
+}
+
+pub fn save_pattern(path: &Path, pattern: &Pattern) -> Result<(), String> {
+    crate::pattern::save(path, pattern)
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+pub enum InputEvent {
+    Editing,
+    Done(String),
+    Cancelled,
+}
+
+// Single line text field typed into from the keyboard
+pub struct TextInput {
+    pub prompt: String,
+    pub text: String,
+    // Characters that can be typed
+    accept: fn(char) -> bool,
+}
+
+impl TextInput {
+    pub fn new(prompt: &str, text: &str, accept: fn(char) -> bool) -> Self {
+        TextInput {
+            prompt: prompt.to_owned(),
+            text: text.to_owned(),
+            accept,
+        }
+    }
+
+    // Handle the keys typed since the last frame
+    pub fn update(&mut self, rl: &mut RaylibHandle) -> InputEvent {
+        loop {
+            // Characters are queued, there may be several per frame
+            // SAFETY: GetCharPressed only pops raylib's input queue, which is
+            // valid while the window owned by `rl` is open
+            let c = unsafe { raylib::ffi::GetCharPressed() };
+            if c <= 0 {
+                break;
+            }
+            if let Some(c) = char::from_u32(c as u32).filter(|c| (self.accept)(*c)) {
+                self.text.push(c);
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.text.pop();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) || rl.is_key_pressed(KeyboardKey::KEY_KP_ENTER)
+        {
+            InputEvent::Done(self.text.clone())
+        } else if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            InputEvent::Cancelled
+        } else {
+            InputEvent::Editing
+        }
+    }
+
+    pub fn draw(&self, d: &mut impl RaylibDraw, x: i32, y: i32, width: i32) {
+        d.draw_rectangle(x, y, width, 20, Color::from_hex("073642").unwrap());
+        d.draw_rectangle_lines(x, y, width, 20, Color::from_hex("b58900").unwrap());
+        d.draw_text(
+            &format!("{} {}_", self.prompt, self.text),
+            x + 4,
+            y + 4,
+            12,
+            Color::from_hex("eee8d5").unwrap(),
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use super::*;
+
+const SIDE: usize = (MAX_COORD - MIN_COORD + 1) as usize;
+
+// How a block of cells is colored when zoomed out past one pixel per cell
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shading {
+    // Most common non-zero state, so that sparse patterns stay visible
+    Majority,
+    // Colors of all cells mixed in proportion
+    Density,
+}
+
+impl Shading {
+    pub fn toggled(self) -> Self {
+        match self {
+            Shading::Majority => Shading::Density,
+            Shading::Density => Shading::Majority,
+        }
+    }
+}
+
+impl std::fmt::Display for Shading {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Shading::Majority => write!(f, "majority"),
+            Shading::Density => write!(f, "density"),
+        }
+    }
+}
+
+// The whole space drawn into a texture with one texel per block of cells.
+// Only blocks with changed cells are recomputed.
+pub struct SpaceTexture {
+    pub shading: Shading,
+    // Side of the block of cells each texel stands for
+    block: usize,
+    // Shading and colors the texels were computed with
+    texture_shading: Shading,
+    texture_palette: Palette,
+    // Hash and cells of the space the texture was last updated with
+    hash: Option<u64>,
+    cells: Vec<u8>,
+    // RGBA texels
+    pixels: Vec<u8>,
+    texture: Option<Texture2D>,
+}
+
+impl SpaceTexture {
+    pub fn new() -> Self {
+        SpaceTexture {
+            shading: Shading::Majority,
+            block: 1,
+            texture_shading: Shading::Majority,
+            texture_palette: Palette::new(),
+            hash: None,
+            cells: vec![0; SIDE * SIDE],
+            pixels: vec![],
+            texture: None,
+        }
+    }
+
+    // Smallest power of two block size that fits in a pixel
+    fn block_for(cell_size: f32) -> usize {
+        let cells_per_pixel = (1.0 / cell_size).ceil() as usize;
+        cells_per_pixel.next_power_of_two().min(SIDE)
+    }
+
+    // Recompute the texture after the space or the zoom changed
+    pub fn update(
+        &mut self,
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        sim: &Simulation,
+        palette: &Palette,
+        cell_size: f32,
+    ) {
+        let block = Self::block_for(cell_size);
+        let side = SIDE / block;
+        let rebuild = self.texture.is_none()
+            || block != self.block
+            || self.shading != self.texture_shading
+            || *palette != self.texture_palette;
+        if !rebuild && self.hash == Some(sim.hash()) {
+            return;
+        }
+        if rebuild {
+            let image = Image::gen_image_color(side as i32, side as i32, cell_color(palette, 0));
+            let texture = rl
+                .load_texture_from_image(thread, &image)
+                .expect("Failed to create the space texture");
+            texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_POINT);
+            texture.set_texture_wrap(thread, TextureWrap::TEXTURE_WRAP_REPEAT);
+            self.texture = Some(texture);
+            self.pixels = vec![0; side * side * 4];
+            self.block = block;
+            self.texture_shading = self.shading;
+            self.texture_palette = palette.clone();
+        }
+
+        let mut dirty = vec![rebuild; side * side];
+        for y in 0..SIDE {
+            for x in 0..SIDE {
+                let cell = sim.get(x as i64 + MIN_COORD, y as i64 + MIN_COORD).0;
+                let idx = y * SIDE + x;
+                if self.cells[idx] != cell {
+                    self.cells[idx] = cell;
+                    dirty[(y / block) * side + x / block] = true;
+                }
+            }
+        }
+        for (texel, _) in dirty.iter().enumerate().filter(|(_, dirty)| **dirty) {
+            let color = self.block_color(palette, texel % side, texel / side);
+            self.pixels[texel * 4..texel * 4 + 4]
+                .copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+        if let Some(texture) = &mut self.texture {
+            texture.update_texture(&self.pixels);
+        }
+        self.hash = Some(sim.hash());
+    }
+
+    fn block_color(&self, palette: &Palette, texel_x: usize, texel_y: usize) -> Color {
+        let block = self.block;
+        let mut counts = [0usize; 256];
+        for y in texel_y * block..(texel_y + 1) * block {
+            for x in texel_x * block..(texel_x + 1) * block {
+                counts[self.cells[y * SIDE + x] as usize] += 1;
+            }
+        }
+        match self.shading {
+            Shading::Majority => counts
+                .iter()
+                .enumerate()
+                .skip(1)
+                .filter(|(_, count)| **count > 0)
+                // The lowest state wins ties
+                .max_by_key(|(state, count)| (**count, std::cmp::Reverse(*state)))
+                .map_or(cell_color(palette, 0), |(state, _)| {
+                    cell_color(palette, state as u8)
+                }),
+            Shading::Density => {
+                let total = (block * block) as f32;
+                let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+                for (state, count) in counts.iter().enumerate().filter(|(_, c)| **c > 0) {
+                    let color = cell_color(palette, state as u8);
+                    let share = *count as f32 / total;
+                    r += color.r as f32 * share;
+                    g += color.g as f32 * share;
+                    b += color.b as f32 * share;
+                }
+                Color::new(r as u8, g as u8, b as u8, 255)
+            }
+        }
+    }
+
+    // Draw the visible cells of the space into the viewport
+    pub fn draw(&self, d: &mut impl RaylibDraw, camera: &Camera) {
+        if let Some(texture) = &self.texture {
+            draw_space_texture(d, texture, self.block, camera);
+        }
+    }
+}
+
+// Draw the visible part of a texture of the whole space, with a texel per
+// block of cells, into the viewport
+pub fn draw_space_texture(
+    d: &mut impl RaylibDraw,
+    texture: &Texture2D,
+    block: usize,
+    camera: &Camera,
+) {
+    let (x_min, x_max, y_min, y_max) = camera.visible_cells();
+    let block = block as f32;
+    let source = Rectangle::new(
+        (x_min - MIN_COORD).rem_euclid(SIDE as i64) as f32 / block,
+        (y_min - MIN_COORD).rem_euclid(SIDE as i64) as f32 / block,
+        (x_max - x_min + 1) as f32 / block,
+        (y_max - y_min + 1) as f32 / block,
+    );
+    let top_left = camera.to_screen(x_min, y_min);
+    let dest = Rectangle::new(
+        top_left.x,
+        top_left.y,
+        (x_max - x_min + 1) as f32 * camera.cell_size,
+        (y_max - y_min + 1) as f32 * camera.cell_size,
+    );
+    d.draw_texture_pro(texture, source, dest, Vector2::zero(), 0.0, Color::WHITE);
+}
@@ -0,0 +1,151 @@
+use super::*;
+
+use super::lod::draw_space_texture;
+use crate::activity::Activity;
+
+const SIDE: usize = (MAX_COORD - MIN_COORD + 1) as usize;
+// Generations it takes a change to fade out of the time since last change
+const RECENT_GENERATIONS: f32 = 64.0;
+
+// Layer of per-cell activity drawn over the cells
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Overlay {
+    None,
+    ChangeFrequency,
+    SinceLastChange,
+    Visited,
+}
+
+impl Overlay {
+    pub fn next(self) -> Self {
+        match self {
+            Overlay::None => Overlay::ChangeFrequency,
+            Overlay::ChangeFrequency => Overlay::SinceLastChange,
+            Overlay::SinceLastChange => Overlay::Visited,
+            Overlay::Visited => Overlay::None,
+        }
+    }
+}
+
+impl std::fmt::Display for Overlay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Overlay::None => write!(f, "none"),
+            Overlay::ChangeFrequency => write!(f, "change frequency"),
+            Overlay::SinceLastChange => write!(f, "time since last change"),
+            Overlay::Visited => write!(f, "ever visited"),
+        }
+    }
+}
+
+fn mix(from: Color, to: Color, t: f32, alpha: u8) -> Color {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    Color::new(
+        channel(from.r, to.r),
+        channel(from.g, to.g),
+        channel(from.b, to.b),
+        alpha,
+    )
+}
+
+// Translucent texture of the whole space with a texel per cell
+pub struct OverlayTexture {
+    pub overlay: Overlay,
+    // Overlay, step and hash of the space the texture was computed for
+    computed: Option<(Overlay, u64, u64)>,
+    // RGBA texels
+    pixels: Vec<u8>,
+    texture: Option<Texture2D>,
+}
+
+impl OverlayTexture {
+    pub fn new() -> Self {
+        OverlayTexture {
+            overlay: Overlay::None,
+            computed: None,
+            pixels: vec![0; SIDE * SIDE * 4],
+            texture: None,
+        }
+    }
+
+    // Recompute the texture after the space or the overlay changed
+    pub fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, sim: &Simulation) {
+        let Some(activity) = sim.activity() else {
+            return;
+        };
+        let key = (self.overlay, sim.current_step(), sim.hash());
+        if self.overlay == Overlay::None || self.computed == Some(key) {
+            return;
+        }
+        if self.texture.is_none() {
+            let image = Image::gen_image_color(SIDE as i32, SIDE as i32, Color::BLANK);
+            let texture = rl
+                .load_texture_from_image(thread, &image)
+                .expect("Failed to create the overlay texture");
+            texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_POINT);
+            texture.set_texture_wrap(thread, TextureWrap::TEXTURE_WRAP_REPEAT);
+            self.texture = Some(texture);
+        }
+
+        for y in 0..SIDE {
+            for x in 0..SIDE {
+                let color = self.cell_color(
+                    activity,
+                    x as i64 + MIN_COORD,
+                    y as i64 + MIN_COORD,
+                    sim.current_step(),
+                );
+                let idx = (y * SIDE + x) * 4;
+                self.pixels[idx..idx + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+        }
+        if let Some(texture) = &mut self.texture {
+            texture.update_texture(&self.pixels);
+        }
+        self.computed = Some(key);
+    }
+
+    fn cell_color(&self, activity: &Activity, x: i64, y: i64, step: u64) -> Color {
+        match self.overlay {
+            Overlay::None => Color::BLANK,
+            Overlay::ChangeFrequency => {
+                let frequency = activity.change_frequency(x, y);
+                if frequency == 0.0 {
+                    return Color::BLANK;
+                }
+                // Rare changes would hardly show on a linear scale
+                let t = frequency.sqrt();
+                mix(
+                    Color::new(181, 137, 0, 255),
+                    Color::new(220, 50, 47, 255),
+                    t,
+                    (60.0 + 160.0 * t) as u8,
+                )
+            }
+            Overlay::SinceLastChange => match activity.last_change(x, y) {
+                None => Color::BLANK,
+                Some(changed) => {
+                    let age = step.saturating_sub(changed) as f32;
+                    let t = 1.0 - (age / RECENT_GENERATIONS).min(1.0);
+                    mix(
+                        Color::new(38, 139, 210, 255),
+                        Color::new(238, 232, 213, 255),
+                        t,
+                        (50.0 + 170.0 * t) as u8,
+                    )
+                }
+            },
+            Overlay::Visited if activity.visited(x, y) => Color::new(42, 161, 152, 115),
+            Overlay::Visited => Color::BLANK,
+        }
+    }
+
+    pub fn draw(&self, d: &mut impl RaylibDraw, camera: &Camera) {
+        if self.overlay == Overlay::None {
+            return;
+        }
+        if let Some(texture) = &self.texture {
+            draw_space_texture(d, texture, 1, camera);
+        }
+    }
+}
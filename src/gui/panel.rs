@@ -0,0 +1,266 @@
+use super::*;
+
+use crate::rules::RULESETS;
+
+const BUTTON_HEIGHT: i32 = 20;
+const SPACING: i32 = 4;
+// States listed one per line, more are shown as a grid of swatches
+const MAX_LEGEND_LINES: usize = 16;
+const GRID_COLUMNS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PanelAction {
+    Step,
+    Run,
+    Pause,
+    Randomize,
+    Clear,
+    Record,
+    PickCell(Cell),
+    PickRuleset(&'static str),
+}
+
+// Buttons, ruleset picker and state legend at the top of the side panel
+pub struct Panel {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    // Whether the list of rulesets is unfolded
+    rulesets_open: bool,
+}
+
+impl Panel {
+    pub fn new(x: i32, y: i32, width: i32) -> Self {
+        Panel {
+            x,
+            y,
+            width,
+            rulesets_open: false,
+        }
+    }
+
+    fn buttons(&self) -> Vec<(Rectangle, &'static str, PanelAction)> {
+        let rows: [&[(&str, PanelAction)]; 3] = [
+            &[
+                ("Step", PanelAction::Step),
+                ("Run", PanelAction::Run),
+                ("Pause", PanelAction::Pause),
+            ],
+            &[
+                ("Randomize", PanelAction::Randomize),
+                ("Clear", PanelAction::Clear),
+            ],
+            &[("Record GIF", PanelAction::Record)],
+        ];
+        let mut buttons = vec![];
+        for (row_idx, row) in rows.iter().enumerate() {
+            let count = row.len() as i32;
+            let width = (self.width - SPACING * (count - 1)) / count;
+            for (idx, (label, action)) in row.iter().enumerate() {
+                let rect = Rectangle::new(
+                    (self.x + (width + SPACING) * idx as i32) as f32,
+                    (self.y + (BUTTON_HEIGHT + SPACING) * row_idx as i32) as f32,
+                    width as f32,
+                    BUTTON_HEIGHT as f32,
+                );
+                buttons.push((rect, *label, *action));
+            }
+        }
+        buttons
+    }
+
+    fn ruleset_button(&self) -> Rectangle {
+        Rectangle::new(
+            self.x as f32,
+            (self.y + (BUTTON_HEIGHT + SPACING) * 3) as f32,
+            self.width as f32,
+            BUTTON_HEIGHT as f32,
+        )
+    }
+
+    // Entries of the unfolded ruleset list, below the ruleset button
+    fn ruleset_items(&self) -> Vec<(Rectangle, &'static str)> {
+        let button = self.ruleset_button();
+        RULESETS
+            .iter()
+            .enumerate()
+            .map(|(idx, info)| {
+                let rect = Rectangle::new(
+                    button.x,
+                    button.y + BUTTON_HEIGHT as f32 * (idx + 1) as f32,
+                    button.width,
+                    BUTTON_HEIGHT as f32,
+                );
+                (rect, info.name)
+            })
+            .collect()
+    }
+
+    fn legend_y(&self) -> i32 {
+        self.y + (BUTTON_HEIGHT + SPACING) * 4
+    }
+
+    // Swatch of each state
+    fn swatches(&self, states: usize) -> Vec<Rectangle> {
+        let y = self.legend_y();
+        if states <= MAX_LEGEND_LINES {
+            return (0..states)
+                .map(|cell| {
+                    Rectangle::new(self.x as f32, (y + 12 * cell as i32 + 1) as f32, 10.0, 10.0)
+                })
+                .collect();
+        }
+        let size = self.width / GRID_COLUMNS as i32;
+        (0..states)
+            .map(|cell| {
+                Rectangle::new(
+                    (self.x + size * (cell % GRID_COLUMNS) as i32) as f32,
+                    (y + size * (cell / GRID_COLUMNS) as i32) as f32,
+                    (size - 1) as f32,
+                    (size - 1) as f32,
+                )
+            })
+            .collect()
+    }
+
+    // Height taken, not counting the unfolded ruleset list
+    pub fn height(&self, states: usize) -> i32 {
+        let legend_height = if states <= MAX_LEGEND_LINES {
+            12 * states as i32
+        } else {
+            let size = self.width / GRID_COLUMNS as i32;
+            size * states.div_ceil(GRID_COLUMNS) as i32
+        };
+        self.legend_y() - self.y + legend_height
+    }
+
+    // Handle a click, any click folds the ruleset list
+    pub fn click(&mut self, pos: Vector2, states: usize) -> Option<PanelAction> {
+        if self.rulesets_open {
+            self.rulesets_open = false;
+            return self
+                .ruleset_items()
+                .into_iter()
+                .find(|(rect, _)| rect.check_collision_point_rec(pos))
+                .map(|(_, name)| PanelAction::PickRuleset(name));
+        }
+        if self.ruleset_button().check_collision_point_rec(pos) {
+            self.rulesets_open = true;
+            return None;
+        }
+        if let Some((_, _, action)) = self
+            .buttons()
+            .into_iter()
+            .find(|(rect, _, _)| rect.check_collision_point_rec(pos))
+        {
+            return Some(action);
+        }
+        // Lines of the legend can be clicked anywhere, not only on the swatch
+        let legend_line = states <= MAX_LEGEND_LINES;
+        self.swatches(states)
+            .into_iter()
+            .position(|rect| {
+                let rect = if legend_line {
+                    Rectangle::new(rect.x, rect.y - 1.0, self.width as f32, 12.0)
+                } else {
+                    rect
+                };
+                rect.check_collision_point_rec(pos)
+            })
+            .map(|cell| PanelAction::PickCell(Cell(cell as u8)))
+    }
+
+    pub fn draw(
+        &self,
+        d: &mut impl RaylibDraw,
+        state: &GuiState,
+        running: bool,
+        recording: bool,
+        mouse_pos: Vector2,
+    ) {
+        let text_color = Color::from_hex("839496").unwrap();
+        let highlight = Color::from_hex("eee8d5").unwrap();
+
+        for (rect, label, action) in self.buttons() {
+            let active = match action {
+                PanelAction::Run => running,
+                PanelAction::Pause => !running,
+                PanelAction::Record => recording,
+                _ => false,
+            };
+            draw_button(
+                d,
+                rect,
+                label,
+                active,
+                rect.check_collision_point_rec(mouse_pos),
+            );
+        }
+        let button = self.ruleset_button();
+        draw_button(
+            d,
+            button,
+            &format!("Rules: {} v", state.sim.ruleset_name()),
+            self.rulesets_open,
+            button.check_collision_point_rec(mouse_pos),
+        );
+
+        let states = state.sim.get_cell_types_count();
+        let population = state
+            .sim
+            .history()
+            .back()
+            .map_or(&[][..], |stats| &stats.population);
+        for (cell, rect) in self.swatches(states).into_iter().enumerate() {
+            d.draw_rectangle_rec(rect, cell_color(&state.palette, cell as u8));
+            if cell == state.current_cell.0 as usize {
+                d.draw_rectangle_lines_ex(
+                    Rectangle::new(
+                        rect.x - 1.0,
+                        rect.y - 1.0,
+                        rect.width + 2.0,
+                        rect.height + 2.0,
+                    ),
+                    1,
+                    highlight,
+                );
+            }
+            if states <= MAX_LEGEND_LINES {
+                let label = match population.get(cell) {
+                    Some(count) if cell > 0 => format!("{}: {}", cell, count),
+                    _ => cell.to_string(),
+                };
+                d.draw_text(&label, self.x + 16, rect.y as i32 - 1, 12, text_color);
+            }
+        }
+
+        if self.rulesets_open {
+            for (rect, name) in self.ruleset_items() {
+                draw_button(
+                    d,
+                    rect,
+                    name,
+                    name == state.sim.ruleset_name(),
+                    rect.check_collision_point_rec(mouse_pos),
+                );
+            }
+        }
+    }
+}
+
+fn draw_button(d: &mut impl RaylibDraw, rect: Rectangle, label: &str, active: bool, hovered: bool) {
+    let background = match (active, hovered) {
+        (true, _) => Color::from_hex("586e75").unwrap(),
+        (false, true) => Color::from_hex("0f4a5a").unwrap(),
+        (false, false) => Color::from_hex("073642").unwrap(),
+    };
+    d.draw_rectangle_rec(rect, background);
+    d.draw_rectangle_lines_ex(rect, 1, Color::from_hex("586e75").unwrap());
+    d.draw_text(
+        label,
+        rect.x as i32 + 6,
+        rect.y as i32 + (rect.height as i32 - 12) / 2,
+        12,
+        Color::from_hex("eee8d5").unwrap(),
+    );
+}
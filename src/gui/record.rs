@@ -0,0 +1,98 @@
+use super::*;
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::export::{color_table, gif::GifEncoder, render, Image, RenderOptions};
+
+// Frames are shown for as long as they were on screen, within these bounds
+// in hundredths of a second
+const MIN_DELAY: f64 = 2.0;
+const MAX_DELAY: f64 = 500.0;
+
+// File in the working directory named after the current time
+fn timestamped_path(extension: &str) -> PathBuf {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_secs());
+    PathBuf::from(format!("cellariella-{}.{}", seconds, extension))
+}
+
+// PNG image of the cells
+pub fn snapshot(
+    sim: &Simulation,
+    palette: &Palette,
+    options: &RenderOptions,
+) -> Result<PathBuf, String> {
+    let path = timestamped_path("png");
+    let colors = color_table(palette, sim.get_cell_types_count());
+    crate::export::png::save(&path, &render(sim, options), &colors)?;
+    Ok(path)
+}
+
+// Animated GIF of the cells that were visible when it started, with a frame
+// per generation shown
+pub struct Recording {
+    pub path: PathBuf,
+    encoder: GifEncoder<BufWriter<File>>,
+    options: RenderOptions,
+    // Last frame along with its step and the time it was shown at, written
+    // once it's known how long it stayed on screen
+    pending: (Image, u64, f64),
+    pub frames: u64,
+}
+
+impl Recording {
+    pub fn start(
+        sim: &Simulation,
+        palette: &Palette,
+        options: RenderOptions,
+        now: f64,
+    ) -> Result<Self, String> {
+        let path = timestamped_path("gif");
+        let image = render(sim, &options);
+        let colors = color_table(palette, sim.get_cell_types_count());
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let encoder = GifEncoder::new(BufWriter::new(file), image.width, image.height, &colors)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(Recording {
+            path,
+            encoder,
+            options,
+            pending: (image, sim.current_step(), now),
+            frames: 0,
+        })
+    }
+
+    fn write_pending(&mut self, now: f64) -> Result<(), String> {
+        let (image, _, shown_at) = &self.pending;
+        let delay = ((now - shown_at) * 100.0).clamp(MIN_DELAY, MAX_DELAY) as u16;
+        self.encoder
+            .add_frame(image, delay)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    // Add a frame when the simulation moved to another generation
+    pub fn update(&mut self, sim: &Simulation, now: f64) -> Result<(), String> {
+        if sim.current_step() == self.pending.1 {
+            return Ok(());
+        }
+        self.write_pending(now)?;
+        self.pending = (render(sim, &self.options), sim.current_step(), now);
+        Ok(())
+    }
+
+    pub fn stop(mut self, now: f64) -> Result<PathBuf, String> {
+        self.write_pending(now)?;
+        self.encoder
+            .finish()
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        Ok(self.path)
+    }
+}
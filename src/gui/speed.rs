@@ -0,0 +1,137 @@
+use super::*;
+
+use std::{collections::VecDeque, time::Instant};
+
+// Ticks per second of the automatic stepping
+const TICK_RATES: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0];
+const DEFAULT_TICK_RATE: usize = 3;
+const MAX_GENERATIONS_PER_TICK: u32 = 1024;
+// Seconds a frame may spend stepping, so that the window stays responsive
+// when fast-forwarding
+const FRAME_BUDGET: f64 = 1.0 / 30.0;
+// Seconds of history the generations per second are measured over
+const RATE_WINDOW: f64 = 1.0;
+
+// Automatic stepping of the simulation
+pub struct Autostep {
+    pub running: bool,
+    tick_rate: usize,
+    pub generations_per_tick: u32,
+    // Step to stop at
+    pub until: Option<u64>,
+    last_tick: f64,
+    // Times and simulation steps of the last frames
+    samples: VecDeque<(f64, u64)>,
+}
+
+impl Autostep {
+    pub fn new() -> Self {
+        Autostep {
+            running: false,
+            tick_rate: DEFAULT_TICK_RATE,
+            generations_per_tick: 1,
+            until: None,
+            last_tick: 0.0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn tick_rate(&self) -> f64 {
+        TICK_RATES[self.tick_rate]
+    }
+
+    pub fn faster(&mut self) {
+        if self.tick_rate + 1 < TICK_RATES.len() {
+            self.tick_rate += 1;
+        } else {
+            self.more_generations();
+        }
+    }
+
+    pub fn slower(&mut self) {
+        if self.generations_per_tick > 1 {
+            self.fewer_generations();
+        } else {
+            self.tick_rate = self.tick_rate.saturating_sub(1);
+        }
+    }
+
+    pub fn more_generations(&mut self) {
+        self.generations_per_tick = (self.generations_per_tick * 2).min(MAX_GENERATIONS_PER_TICK);
+    }
+
+    pub fn fewer_generations(&mut self) {
+        self.generations_per_tick = (self.generations_per_tick / 2).max(1);
+    }
+
+    pub fn start(&mut self, now: f64) {
+        self.running = true;
+        self.last_tick = now;
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.until = None;
+    }
+
+    // Run up to the step, or do nothing if it was already reached
+    pub fn run_until(&mut self, step: u64, current_step: u64, now: f64) {
+        if step > current_step {
+            self.until = Some(step);
+            self.start(now);
+        }
+    }
+
+    fn reached(&self, sim: &Simulation) -> bool {
+        self.until.is_some_and(|until| sim.current_step() >= until)
+    }
+
+    // Step the simulation when a tick is due
+    pub fn update(&mut self, state: &mut GuiState, now: f64) {
+        if self.running && now - self.last_tick >= 1.0 / self.tick_rate() {
+            self.last_tick = now;
+            let started = Instant::now();
+            for _ in 0..self.generations_per_tick {
+                if self.reached(&state.sim) {
+                    break;
+                }
+                state.step();
+                if started.elapsed().as_secs_f64() > FRAME_BUDGET {
+                    break;
+                }
+            }
+            if self.reached(&state.sim) {
+                self.stop();
+            }
+        }
+
+        self.samples.push_back((now, state.sim.current_step()));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(time, _)| *time < now - RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    // Measured over the last second, manual steps included
+    pub fn generations_per_second(&self) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((t0, step0)), Some((t1, step1))) if t1 > t0 => {
+                step1.saturating_sub(*step0) as f64 / (t1 - t0)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+impl std::fmt::Display for Autostep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ticks/s", self.tick_rate())?;
+        if self.generations_per_tick > 1 {
+            write!(f, " x {} gens", self.generations_per_tick)?;
+        }
+        Ok(())
+    }
+}
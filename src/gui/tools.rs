@@ -0,0 +1,415 @@
+use super::*;
+
+use std::path::PathBuf;
+
+use log::warn;
+
+use super::editor::{paste, PasteMode};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tool {
+    Pencil,
+    Line,
+    Rectangle,
+    FilledRectangle,
+    // Replace the connected area of cells in the clicked state
+    FloodFill,
+    // Place the current pattern of the library
+    Stamp,
+}
+
+const TOOLS: &[Tool] = &[
+    Tool::Pencil,
+    Tool::Line,
+    Tool::Rectangle,
+    Tool::FilledRectangle,
+    Tool::FloodFill,
+    Tool::Stamp,
+];
+
+impl Tool {
+    pub fn next(self) -> Self {
+        let idx = TOOLS.iter().position(|t| *t == self).unwrap();
+        TOOLS[(idx + 1) % TOOLS.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let idx = TOOLS.iter().position(|t| *t == self).unwrap();
+        TOOLS[(idx + TOOLS.len() - 1) % TOOLS.len()]
+    }
+}
+
+impl std::fmt::Display for Tool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tool::Pencil => write!(f, "pencil"),
+            Tool::Line => write!(f, "line"),
+            Tool::Rectangle => write!(f, "rectangle"),
+            Tool::FilledRectangle => write!(f, "filled rectangle"),
+            Tool::FloodFill => write!(f, "flood fill"),
+            Tool::Stamp => write!(f, "stamp"),
+        }
+    }
+}
+
+// Cells of a straight line between two cells (Bresenham)
+pub fn line_cells(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let (step_x, step_y) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let (mut x, mut y) = from;
+    let mut error = dx + dy;
+    let mut cells = vec![(x, y)];
+    while (x, y) != to {
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+        cells.push((x, y));
+    }
+    cells
+}
+
+// Cells of the rectangle with the two cells as opposite corners
+pub fn rectangle_cells(a: (i64, i64), b: (i64, i64), filled: bool) -> Vec<(i64, i64)> {
+    let (x_min, x_max) = (a.0.min(b.0), a.0.max(b.0));
+    let (y_min, y_max) = (a.1.min(b.1), a.1.max(b.1));
+    let mut cells = vec![];
+    for y in y_min..=y_max {
+        for x in x_min..=x_max {
+            if filled || x == x_min || x == x_max || y == y_min || y == y_max {
+                cells.push((x, y));
+            }
+        }
+    }
+    cells
+}
+
+// Replace the cells orthogonally connected to (x, y) in the same state
+pub fn flood_fill(sim: &mut Simulation, x: i64, y: i64, cell: Cell) {
+    let target = sim.get(x, y);
+    if target == cell {
+        return;
+    }
+    let mut queue = vec![(wrap(x), wrap(y))];
+    sim.set(x, y, cell);
+    while let Some((x, y)) = queue.pop() {
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let (nx, ny) = (wrap(x + dx), wrap(y + dy));
+            if sim.get(nx, ny) == target {
+                sim.set(nx, ny, cell);
+                queue.push((nx, ny));
+            }
+        }
+    }
+}
+
+// Patterns for the stamp tool, loaded from a directory
+pub struct Library {
+    dir: Option<PathBuf>,
+    // File names and patterns, sorted by name
+    pub patterns: Vec<(String, Pattern)>,
+    pub current: usize,
+}
+
+impl Library {
+    pub fn load(dir: Option<PathBuf>) -> Self {
+        let mut patterns = vec![];
+        if let Some(dir) = &dir {
+            match std::fs::read_dir(dir) {
+                Ok(entries) => {
+                    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+                        if !path.is_file() {
+                            continue;
+                        }
+                        match crate::pattern::load(&path) {
+                            Ok(pattern) => patterns.push((file_name(&path), pattern)),
+                            Err(e) => warn!("Skipping {}: {}", path.display(), e),
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to read the library {}: {}", dir.display(), e),
+            }
+        }
+        patterns.sort_by(|a, b| a.0.cmp(&b.0));
+        Library {
+            dir,
+            patterns,
+            current: 0,
+        }
+    }
+
+    pub fn current(&self) -> Option<&Pattern> {
+        self.patterns.get(self.current).map(|(_, pattern)| pattern)
+    }
+
+    pub fn current_name(&self) -> Option<&str> {
+        self.patterns
+            .get(self.current)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.patterns.len().max(1);
+    }
+
+    pub fn previous(&mut self) {
+        let len = self.patterns.len().max(1);
+        self.current = (self.current + len - 1) % len;
+    }
+
+    pub fn transform_current(&mut self, f: impl Fn(&Pattern) -> Pattern) {
+        if let Some((_, pattern)) = self.patterns.get_mut(self.current) {
+            *pattern = f(pattern);
+        }
+    }
+
+    // Add the pattern and make it current, saving it to the library
+    // directory if there is one
+    pub fn add(&mut self, pattern: Pattern) {
+        let name = (1..)
+            .map(|n| format!("stamp-{}.rle", n))
+            .find(|name| {
+                self.patterns.iter().all(|(n, _)| n != name)
+                    && self
+                        .dir
+                        .as_ref()
+                        .map_or(true, |dir| !dir.join(name).exists())
+            })
+            .unwrap();
+        if let Some(dir) = &self.dir {
+            let path = dir.join(&name);
+            if let Err(e) = crate::pattern::save(&path, &pattern) {
+                warn!("Failed to save {}: {}", path.display(), e);
+            }
+        }
+        self.patterns.push((name, pattern));
+        self.current = self.patterns.len() - 1;
+    }
+}
+
+fn file_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Mouse drag in progress
+struct Drag {
+    from: (i64, i64),
+    to: (i64, i64),
+    // State being drawn with, zero when drawing with the right button
+    cell: Cell,
+    // Hash of the space before a pencil stroke, to tell if it changed anything
+    stroke: Option<u64>,
+}
+
+pub struct Tools {
+    pub tool: Tool,
+    pub library: Library,
+    drag: Option<Drag>,
+}
+
+impl Tools {
+    pub fn new(library: Library) -> Self {
+        Tools {
+            tool: Tool::Pencil,
+            library,
+            drag: None,
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    // Top left corner of the stamp centered on the cell
+    fn stamp_at(&self, x: i64, y: i64) -> Option<(&Pattern, i64, i64)> {
+        let pattern = self.library.current()?;
+        let (x0, y0) = super::editor::centered_at(pattern, x, y);
+        Some((pattern, x0, y0))
+    }
+
+    // A mouse button went down over the cell
+    pub fn press(&mut self, state: &mut GuiState, x: i64, y: i64, cell: Cell, mode: PasteMode) {
+        let stroke = (self.tool == Tool::Pencil).then(|| state.sim.hash());
+        match self.tool {
+            Tool::Pencil => {
+                state.undo.checkpoint(&state.sim);
+                state.sim.set(x, y, cell);
+                state.edited();
+            }
+            Tool::Line | Tool::Rectangle | Tool::FilledRectangle => {}
+            Tool::FloodFill => {
+                state.edit(|sim| flood_fill(sim, x, y, cell));
+                return;
+            }
+            Tool::Stamp => {
+                if let Some((pattern, x0, y0)) = self.stamp_at(x, y) {
+                    state.edit(|sim| paste(sim, pattern, x0, y0, mode));
+                }
+                return;
+            }
+        }
+        self.drag = Some(Drag {
+            from: (x, y),
+            to: (x, y),
+            cell,
+            stroke,
+        });
+    }
+
+    // The mouse moved to the cell with the button still down
+    pub fn drag(&mut self, state: &mut GuiState, x: i64, y: i64) {
+        let Some(drag) = &mut self.drag else {
+            return;
+        };
+        if self.tool == Tool::Pencil && drag.to != (x, y) {
+            // Connect the cells the mouse skipped over
+            for (x, y) in line_cells(drag.to, (x, y)) {
+                state.sim.set(x, y, drag.cell);
+            }
+            state.edited();
+        }
+        drag.to = (x, y);
+    }
+
+    // The mouse button was released
+    pub fn release(&mut self, state: &mut GuiState) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+        if let Some(hash) = drag.stroke {
+            if state.sim.hash() == hash {
+                state.undo.discard();
+            } else {
+                state.undo.commit();
+            }
+            return;
+        }
+        let cells = self.shape(&drag);
+        if !cells.is_empty() {
+            state.edit(|sim| {
+                for (x, y) in cells {
+                    sim.set(x, y, drag.cell);
+                }
+            });
+        }
+    }
+
+    // Cells a line or rectangle drag will draw once released
+    fn shape(&self, drag: &Drag) -> Vec<(i64, i64)> {
+        match self.tool {
+            Tool::Line => line_cells(drag.from, drag.to),
+            Tool::Rectangle => rectangle_cells(drag.from, drag.to, false),
+            Tool::FilledRectangle => rectangle_cells(drag.from, drag.to, true),
+            _ => vec![],
+        }
+    }
+
+    // Cells the tool would draw, shown under the mouse before they are drawn
+    pub fn preview(&self, mouse_x: i64, mouse_y: i64, current_cell: Cell) -> Vec<(i64, i64, Cell)> {
+        if let Some(drag) = &self.drag {
+            return self
+                .shape(drag)
+                .into_iter()
+                .map(|(x, y)| (x, y, drag.cell))
+                .collect();
+        }
+        match self.tool {
+            Tool::Stamp => {
+                let Some((pattern, x0, y0)) = self.stamp_at(mouse_x, mouse_y) else {
+                    return vec![];
+                };
+                let mut cells = vec![];
+                for y in 0..pattern.height() {
+                    for x in 0..pattern.width() {
+                        let cell = pattern.get(x, y);
+                        if cell.0 != 0 {
+                            cells.push((x0 + x, y0 + y, cell));
+                        }
+                    }
+                }
+                cells
+            }
+            _ => vec![(mouse_x, mouse_y, current_cell)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(sim: &Simulation, cell: Cell) -> usize {
+        (MIN_COORD..=MAX_COORD)
+            .flat_map(|y| (MIN_COORD..=MAX_COORD).map(move |x| (x, y)))
+            .filter(|&(x, y)| sim.get(x, y) == cell)
+            .count()
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(line_cells((2, 3), (2, 3)), [(2, 3)]);
+        assert_eq!(line_cells((0, 0), (3, 0)), [(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(line_cells((0, 0), (-2, -2)), [(0, 0), (-1, -1), (-2, -2)]);
+        assert_eq!(
+            line_cells((0, 0), (5, 2)),
+            [(0, 0), (1, 0), (2, 1), (3, 1), (4, 2), (5, 2)]
+        );
+        // Steep lines have a cell per row, in either direction
+        let steep = line_cells((0, 0), (2, 5));
+        assert_eq!(steep.len(), 6);
+        assert_eq!(line_cells((2, 5), (0, 0)).len(), 6);
+        assert!(steep
+            .windows(2)
+            .all(|w| (w[1].0 - w[0].0).abs() <= 1 && w[1].1 - w[0].1 == 1));
+    }
+
+    #[test]
+    fn rectangles() {
+        assert_eq!(rectangle_cells((0, 0), (0, 0), false), [(0, 0)]);
+        assert_eq!(rectangle_cells((3, 2), (0, 0), true).len(), 12);
+        let outline = rectangle_cells((3, 2), (0, 0), false);
+        assert_eq!(outline.len(), 10);
+        assert!(!outline.contains(&(1, 1)) && !outline.contains(&(2, 1)));
+        assert_eq!(rectangle_cells((0, 0), (4, 1), false).len(), 10);
+    }
+
+    #[test]
+    fn enclosed_flood_fill() {
+        let mut sim = Simulation::new(crate::rules::create_ruleset("brain").unwrap());
+        for (x, y) in rectangle_cells((0, 0), (4, 4), false) {
+            sim.set(x, y, Cell(1));
+        }
+        flood_fill(&mut sim, 2, 2, Cell(2));
+        assert_eq!(count(&sim, Cell(2)), 9);
+        assert_eq!(sim.get(5, 2), Cell(0));
+        // Filling with the same state changes nothing
+        flood_fill(&mut sim, 2, 2, Cell(2));
+        assert_eq!(count(&sim, Cell(2)), 9);
+    }
+
+    #[test]
+    fn wrapped_flood_fill() {
+        let mut sim = Simulation::new(crate::rules::create_ruleset("life").unwrap());
+        // A wall across the space, the fill goes around it over the edge
+        for y in MIN_COORD..=MAX_COORD {
+            sim.set(0, y, Cell(1));
+        }
+        flood_fill(&mut sim, MAX_COORD, MAX_COORD, Cell(1));
+        assert_eq!(count(&sim, Cell(0)), 0);
+
+        let mut sim = Simulation::new(crate::rules::create_ruleset("life").unwrap());
+        for y in MIN_COORD..=MAX_COORD {
+            sim.set(0, y, Cell(1));
+            sim.set(10, y, Cell(1));
+        }
+        flood_fill(&mut sim, 5, 300, Cell(1));
+        assert_eq!(count(&sim, Cell(1)), 11 * 256);
+    }
+}
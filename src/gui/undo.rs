@@ -0,0 +1,143 @@
+use super::*;
+
+// Edits kept for undoing, each snapshot holds the whole space
+const MAX_UNDO: usize = 100;
+
+// Snapshots of the space taken before each edit
+pub struct UndoStack {
+    undo: Vec<Pattern>,
+    redo: Vec<Pattern>,
+}
+
+fn snapshot(sim: &Simulation) -> Pattern {
+    let mut pattern = Pattern::new(MAX_COORD - MIN_COORD + 1, MAX_COORD - MIN_COORD + 1);
+    for y in MIN_COORD..=MAX_COORD {
+        for x in MIN_COORD..=MAX_COORD {
+            pattern.set(x - MIN_COORD, y - MIN_COORD, sim.get(x, y));
+        }
+    }
+    pattern
+}
+
+impl UndoStack {
+    pub fn new() -> Self {
+        UndoStack {
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    // Remember the space as it is before an edit, followed by either
+    // commit() or discard() once it's known whether the edit changed it
+    pub fn checkpoint(&mut self, sim: &Simulation) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(snapshot(sim));
+    }
+
+    // Keep the last checkpoint, the undone edits can't be redone anymore
+    pub fn commit(&mut self) {
+        self.redo.clear();
+    }
+
+    // Forget the last checkpoint, for edits that didn't change anything
+    pub fn discard(&mut self) {
+        self.undo.pop();
+    }
+
+    // Returns false if there was nothing to undo
+    pub fn undo(&mut self, sim: &mut Simulation) -> bool {
+        let Some(pattern) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(snapshot(sim));
+        pattern.place(sim, MIN_COORD, MIN_COORD);
+        true
+    }
+
+    // Returns false if there was nothing to redo
+    pub fn redo(&mut self, sim: &mut Simulation) -> bool {
+        let Some(pattern) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(snapshot(sim));
+        pattern.place(sim, MIN_COORD, MIN_COORD);
+        true
+    }
+
+    // Forget all edits, for when the snapshots no longer fit the ruleset
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(undo: &mut UndoStack, sim: &mut Simulation, x: i64) {
+        undo.checkpoint(sim);
+        sim.set(x, 0, Cell(1));
+        undo.commit();
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut sim = Simulation::new(crate::rules::create_ruleset("life").unwrap());
+        let mut undo = UndoStack::new();
+        assert!(!undo.undo(&mut sim));
+        assert!(!undo.redo(&mut sim));
+
+        edit(&mut undo, &mut sim, 0);
+        edit(&mut undo, &mut sim, 1);
+        assert!(undo.undo(&mut sim));
+        assert_eq!((sim.get(0, 0), sim.get(1, 0)), (Cell(1), Cell(0)));
+        assert!(undo.undo(&mut sim));
+        assert_eq!(sim.bounding_box(), None);
+        assert!(!undo.undo(&mut sim));
+
+        assert!(undo.redo(&mut sim));
+        assert!(undo.redo(&mut sim));
+        assert_eq!((sim.get(0, 0), sim.get(1, 0)), (Cell(1), Cell(1)));
+        assert!(!undo.redo(&mut sim));
+
+        // A new edit drops what was undone
+        assert!(undo.undo(&mut sim));
+        edit(&mut undo, &mut sim, 5);
+        assert!(!undo.redo(&mut sim));
+        assert!(undo.undo(&mut sim));
+        assert_eq!((sim.get(1, 0), sim.get(5, 0)), (Cell(0), Cell(0)));
+    }
+
+    #[test]
+    fn discarded_checkpoints() {
+        let mut sim = Simulation::new(crate::rules::create_ruleset("life").unwrap());
+        let mut undo = UndoStack::new();
+        edit(&mut undo, &mut sim, 0);
+        assert!(undo.undo(&mut sim));
+        // An edit that changed nothing keeps the redo
+        undo.checkpoint(&sim);
+        undo.discard();
+        assert!(undo.redo(&mut sim));
+        assert_eq!(sim.get(0, 0), Cell(1));
+    }
+
+    #[test]
+    fn limited_history() {
+        let mut sim = Simulation::new(crate::rules::create_ruleset("life").unwrap());
+        let mut undo = UndoStack::new();
+        for x in 0..MAX_UNDO as i64 + 5 {
+            edit(&mut undo, &mut sim, x);
+        }
+        let mut undone = 0;
+        while undo.undo(&mut sim) {
+            undone += 1;
+        }
+        assert_eq!(undone, MAX_UNDO);
+        // The oldest edits stay
+        assert_eq!(sim.get(4, 0), Cell(1));
+        assert_eq!(sim.get(5, 0), Cell(0));
+    }
+}
@@ -1,41 +1,720 @@
-use clap::Parser;
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+use analysis::{
+    damage::DamageConfig,
+    soup::SoupConfig,
+    spatial::{SpatialConfig, MAX_BLOCK_SIZE},
+    sweep::{RuleFamily, SweepConfig},
+    AnalysisConfig,
+};
+use clap::{Parser, Subcommand};
 use env_logger::Env;
-use gui::gui_main;
+use export::{ExportConfig, Output, Region};
+use gui::{gui_main, GuiOptions};
+use log::{error, info};
+use palette::Palette;
+use pattern::Pattern;
+use report::{format_report, OutputFormat};
+use runner::RunConfig;
+use sim::{RandomFill, Simulation};
+use space::{MAX_COORD, MIN_COORD};
 
+mod activity;
 mod analysis;
+mod export;
 mod gui;
+mod palette;
+mod pattern;
+mod periodicity;
+mod report;
 mod rules;
+mod runner;
 mod sim;
 mod space;
+mod stats;
 
 #[derive(Parser, Debug)]
+#[command(version, about)]
 struct Args {
-    /// Ruleset (e.g. wireworld)
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Args, Debug)]
+struct SimulationArgs {
+    /// Ruleset (see `rules list`), defaults to the rule of the pattern
+    #[arg(short, long, value_parser = parse_ruleset)]
+    ruleset: Option<String>,
+    /// Pattern file (RLE, macrocell, plaintext or Life 1.0x) to load,
+    /// defaults to the initial pattern of the ruleset
     #[arg(short, long)]
-    ruleset: String,
-    /// Run analysis instead of launching the GUI
+    pattern: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Launch the interactive GUI
+    Gui {
+        #[command(flatten)]
+        simulation: SimulationArgs,
+        /// Directory of patterns for the stamp tool
+        #[arg(short, long)]
+        library: Option<PathBuf>,
+        /// Cell colors, as a Golly palette file, a `.rule` file with a
+        /// `@COLORS` section, or a directory of `<ruleset>.colors` and
+        /// `<ruleset>.rule` files
+        #[arg(short = 'c', long)]
+        palette: Option<PathBuf>,
+    },
+    /// Run the simulation without a GUI and write the final state
+    Run {
+        #[command(flatten)]
+        simulation: SimulationArgs,
+        /// Number of generations to run
+        #[arg(short, long)]
+        generations: u64,
+        /// Stop early once the pattern becomes static or periodic
+        #[arg(short, long)]
+        until_stable: bool,
+        /// With --until-stable, also stop when the pattern repeats at an offset (spaceships)
+        #[arg(short = 'T', long, requires = "until_stable")]
+        translation_invariant: bool,
+        /// Output pattern file (format is picked by extension), RLE to stdout if not set
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Write per-generation population statistics to a CSV file
+        #[arg(short, long)]
+        stats: Option<PathBuf>,
+    },
+    /// Collect cell transition statistics on random soups, or run another analysis
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Analyze {
+        /// Ruleset (see `rules list`)
+        #[arg(short, long, value_parser = parse_ruleset, required = true)]
+        ruleset: Option<String>,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+        #[command(subcommand)]
+        mode: Option<AnalyzeMode>,
+    },
+    /// Render the simulation to a PNG image, an animated GIF or numbered PNG frames
+    Export {
+        #[command(flatten)]
+        simulation: SimulationArgs,
+        #[command(flatten)]
+        export: ExportArgs,
+    },
+    /// Convert a pattern file to another format (picked by extension)
+    Convert { input: PathBuf, output: PathBuf },
+    /// Inspect the available rulesets
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+    /// Measure simulation speed on a random soup
+    Bench {
+        /// Ruleset (see `rules list`)
+        #[arg(short, long, value_parser = parse_ruleset)]
+        ruleset: String,
+        /// Number of generations to run
+        #[arg(short, long, default_value_t = 100)]
+        generations: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AnalyzeMode {
+    /// Census the objects left over by many small random soups
+    Soup {
+        /// Ruleset (see `rules list`)
+        #[arg(short, long, value_parser = parse_ruleset)]
+        ruleset: String,
+        #[command(flatten)]
+        soup: SoupArgs,
+    },
+    /// Follow how a single flipped cell spreads through a random soup
+    Damage {
+        /// Ruleset (see `rules list`)
+        #[arg(short, long, value_parser = parse_ruleset)]
+        ruleset: String,
+        #[command(flatten)]
+        damage: DamageArgs,
+    },
+    /// Follow block entropy, correlations and cluster sizes of random soups
+    Spatial {
+        /// Ruleset (see `rules list`)
+        #[arg(short, long, value_parser = parse_ruleset)]
+        ruleset: String,
+        #[command(flatten)]
+        spatial: SpatialArgs,
+    },
+    /// Classify the behavior of many rules of a family, most complex first
+    Sweep {
+        #[command(flatten)]
+        sweep: SweepArgs,
+    },
+}
+
+// Options shared by the analyses of random soups. Their defaults differ
+// between analyses, options left unset are taken from its configuration.
+#[derive(clap::Args, Debug)]
+struct SoupOptions {
+    /// Side of the square random soup [default: depends on the analysis]
+    #[arg(
+        short,
+        long,
+        value_parser = clap::value_parser!(i64).range(1..=MAX_COORD - MIN_COORD + 1),
+    )]
+    size: Option<i64>,
+    /// Probability of a soup cell being non-zero [default: depends on the analysis]
+    #[arg(short, long, value_parser = parse_probability)]
+    density: Option<f32>,
+    /// Comma-separated relative weights of the non-zero states, uniform if not set
+    #[arg(short, long, value_delimiter = ',')]
+    weights: Vec<f32>,
+    /// Random seed, picked at random if not set
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Format of the results
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    output_format: OutputFormat,
+}
+
+impl SoupOptions {
+    fn size(&self, default: i64) -> i64 {
+        self.size.unwrap_or(default)
+    }
+
+    fn fill(&self, default: &RandomFill, states: usize) -> Result<RandomFill, String> {
+        let fill = RandomFill {
+            density: self.density.unwrap_or(default.density),
+            weights: self.weights.clone(),
+        };
+        fill.state_weights(states)?;
+        Ok(fill)
+    }
+
+    fn seed(&self) -> u64 {
+        self.seed.unwrap_or_else(|| {
+            let seed = rand::random();
+            info!("Using seed {}", seed);
+            seed
+        })
+    }
+}
+
+// Number of trials of the analyses averaging several soups
+#[derive(clap::Args, Debug)]
+struct TrialOptions {
+    /// Number of independent trials to average [default: depends on the analysis]
+    #[arg(
+        short,
+        long,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+    )]
+    trials: Option<usize>,
+}
+
+#[derive(clap::Args, Debug)]
+struct AnalysisArgs {
+    /// Number of generations per trial
+    #[arg(short, long, default_value_t = AnalysisConfig::default().iterations)]
+    iterations: usize,
+    #[command(flatten)]
+    soup: SoupOptions,
+    #[command(flatten)]
+    trials: TrialOptions,
+}
+
+impl AnalysisArgs {
+    fn to_config(&self, states: usize) -> Result<AnalysisConfig, String> {
+        let default = AnalysisConfig::default();
+        Ok(AnalysisConfig {
+            iterations: self.iterations,
+            region_size: self.soup.size(default.region_size),
+            fill: self.soup.fill(&default.fill, states)?,
+            seed: self.soup.seed(),
+            trials: self.trials.trials.unwrap_or(default.trials),
+        })
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct SoupArgs {
+    /// Number of soups to run
+    #[arg(short = 'n', long, default_value_t = SoupConfig::default().soups)]
+    soups: usize,
+    #[command(flatten)]
+    soup: SoupOptions,
+    /// Generations to wait for a soup to stabilize
+    #[arg(short = 'g', long, default_value_t = SoupConfig::default().max_generations)]
+    max_generations: u64,
+}
+
+impl SoupArgs {
+    fn to_config(&self, states: usize) -> Result<SoupConfig, String> {
+        let default = SoupConfig::default();
+        Ok(SoupConfig {
+            soups: self.soups,
+            soup_size: self.soup.size(default.soup_size),
+            fill: self.soup.fill(&default.fill, states)?,
+            // Seed of the first soup
+            seed: self.soup.seed(),
+            max_generations: self.max_generations,
+        })
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct DamageArgs {
+    /// Number of generations per trial
+    #[arg(short, long, default_value_t = DamageConfig::default().generations)]
+    generations: u64,
+    #[command(flatten)]
+    soup: SoupOptions,
+    #[command(flatten)]
+    trials: TrialOptions,
+}
+
+impl DamageArgs {
+    fn to_config(&self, states: usize) -> Result<DamageConfig, String> {
+        let default = DamageConfig::default();
+        Ok(DamageConfig {
+            generations: self.generations,
+            region_size: self.soup.size(default.region_size),
+            fill: self.soup.fill(&default.fill, states)?,
+            seed: self.soup.seed(),
+            trials: self.trials.trials.unwrap_or(default.trials),
+        })
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct SpatialArgs {
+    /// Number of generations per trial
+    #[arg(short, long, default_value_t = SpatialConfig::default().generations)]
+    generations: u64,
+    /// Compute the metrics every this many generations
+    #[arg(
+        short,
+        long,
+        default_value_t = SpatialConfig::default().interval,
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    interval: u64,
+    #[command(flatten)]
+    soup: SoupOptions,
+    #[command(flatten)]
+    trials: TrialOptions,
+    /// Largest side of the square blocks to compute the entropy of
+    #[arg(
+        short,
+        long,
+        default_value_t = SpatialConfig::default().block_size as u64,
+        value_parser = clap::value_parser!(u64).range(1..=MAX_BLOCK_SIZE as u64),
+    )]
+    block_size: u64,
+    /// Largest distance between cells to compute the correlation of
+    #[arg(
+        short = 'D',
+        long,
+        default_value_t = SpatialConfig::default().max_distance as u64,
+        value_parser = clap::value_parser!(u64).range(1..=((MAX_COORD - MIN_COORD + 1) / 2) as u64),
+    )]
+    max_distance: u64,
+}
+
+impl SpatialArgs {
+    fn to_config(&self, states: usize) -> Result<SpatialConfig, String> {
+        let default = SpatialConfig::default();
+        Ok(SpatialConfig {
+            generations: self.generations,
+            interval: self.interval,
+            region_size: self.soup.size(default.region_size),
+            fill: self.soup.fill(&default.fill, states)?,
+            seed: self.soup.seed(),
+            trials: self.trials.trials.unwrap_or(default.trials),
+            block_size: self.block_size as usize,
+            max_distance: self.max_distance as usize,
+        })
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct SweepArgs {
+    /// Family of rules to explore
+    #[arg(short, long, value_enum, default_value_t = SweepConfig::default().family)]
+    family: RuleFamily,
+    /// Number of states of totalistic rules
+    #[arg(long, default_value_t = SweepConfig::default().states)]
+    states: usize,
+    /// Number of rules to sample
+    #[arg(short = 'n', long, default_value_t = SweepConfig::default().rules.unwrap())]
+    rules: usize,
+    /// Enumerate all rules of the family instead of sampling (life-like only)
+    #[arg(long, conflicts_with = "rules")]
+    all: bool,
+    /// Number of generations per trial
+    #[arg(short, long, default_value_t = SweepConfig::default().generations)]
+    generations: u64,
+    // The seed is used for sampling rules too
+    #[command(flatten)]
+    soup: SoupOptions,
+    #[command(flatten)]
+    trials: TrialOptions,
+}
+
+impl SweepArgs {
+    fn to_config(&self) -> Result<SweepConfig, String> {
+        let default = SweepConfig::default();
+        let states = match self.family {
+            RuleFamily::LifeLike => 2,
+            RuleFamily::Totalistic => self.states,
+        };
+        Ok(SweepConfig {
+            family: self.family,
+            states: self.states,
+            rules: if self.all { None } else { Some(self.rules) },
+            region_size: self.soup.size(default.region_size),
+            fill: self.soup.fill(&default.fill, states)?,
+            generations: self.generations,
+            seed: self.soup.seed(),
+            trials: self.trials.trials.unwrap_or(default.trials),
+        })
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct ExportArgs {
+    /// Output file, a `.gif` is animated and a `.png` shows the first frame
+    output: PathBuf,
+    /// Write each frame to a numbered PNG file next to the output instead
+    #[arg(long)]
+    sequence: bool,
+    /// Generations to run before the first frame
+    #[arg(long, default_value_t = 0)]
+    skip: u64,
+    /// Number of frames of an animation or sequence
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 100,
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    frames: u64,
+    /// Generations between frames
+    #[arg(
+        short,
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    every: u64,
+    /// Side of a cell in pixels
+    #[arg(
+        short,
+        long,
+        default_value_t = 4,
+        value_parser = clap::value_parser!(u64).range(1..=64),
+    )]
+    scale: u64,
+    /// Draw lines between the cells
     #[arg(short, long)]
-    analyze: bool,
+    grid: bool,
+    /// Cells to render as `x_min,y_min,x_max,y_max`, inclusive, defaults to
+    /// the cells the pattern reaches over the exported frames
+    #[arg(long, value_parser = parse_region, allow_hyphen_values = true)]
+    region: Option<Region>,
+    /// Cell colors, as a Golly palette file, a `.rule` file with a
+    /// `@COLORS` section, or a directory of `<ruleset>.colors` and
+    /// `<ruleset>.rule` files
+    #[arg(short = 'c', long)]
+    palette: Option<PathBuf>,
+    /// Milliseconds between the frames of a GIF, rounded down to hundredths of a second
+    #[arg(short, long, default_value_t = 100)]
+    delay: u64,
 }
 
-fn main() {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+impl ExportArgs {
+    fn to_config(&self) -> ExportConfig {
+        ExportConfig {
+            skip: self.skip,
+            frames: self.frames,
+            every: self.every,
+            region: self.region,
+            scale: self.scale as usize,
+            grid: self.grid,
+            delay_ms: self.delay,
+        }
+    }
 
-    let args = Args::parse();
+    fn to_output(&self) -> Result<Output, String> {
+        let extension = self
+            .output
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("png") if self.sequence => Ok(Output::Sequence(self.output.clone())),
+            Some("png") => Ok(Output::Image(self.output.clone())),
+            Some("gif") if !self.sequence => Ok(Output::Animation(self.output.clone())),
+            Some("gif") => Err("Sequences are written as PNG files".to_owned()),
+            _ => Err(format!(
+                "Unknown image format of {}, expected .png or .gif",
+                self.output.display()
+            )),
+        }
+    }
+}
+
+// Random fill for a ruleset with the given number of states
+#[derive(Subcommand, Debug)]
+enum RulesCommand {
+    /// List the available rulesets
+    List,
+}
+
+fn parse_ruleset(name: &str) -> Result<String, String> {
+    if crate::rules::create_ruleset(name).is_some() {
+        return Ok(name.to_owned());
+    }
+    let known = crate::rules::RULESETS
+        .iter()
+        .map(|info| info.name)
+        .collect::<Vec<_>>();
+    Err(format!(
+        "unknown ruleset (available: {}, or a life-like rule such as B36/S23)",
+        known.join(", ")
+    ))
+}
+
+fn parse_probability(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err("expected a number between 0 and 1".to_owned()),
+    }
+}
+
+// Inclusive corners `x_min,y_min,x_max,y_max`, as (x_min, x_max, y_min, y_max)
+fn parse_region(value: &str) -> Result<Region, String> {
+    let coords = value
+        .split(',')
+        .map(|v| v.trim().parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "expected x_min,y_min,x_max,y_max".to_owned())?;
+    let [x_min, y_min, x_max, y_max] = coords[..] else {
+        return Err("expected x_min,y_min,x_max,y_max".to_owned());
+    };
+    if !coords.iter().all(|v| (MIN_COORD..=MAX_COORD).contains(v)) {
+        return Err(format!(
+            "coordinates must be between {} and {}",
+            MIN_COORD, MAX_COORD
+        ));
+    }
+    if x_min > x_max || y_min > y_max {
+        return Err("the minimum coordinates must not exceed the maximum ones".to_owned());
+    }
+    Ok((x_min, x_max, y_min, y_max))
+}
 
-    let ruleset = crate::rules::create_ruleset(&args.ruleset).expect("Unknown ruleset");
+fn load_pattern(path: &Path) -> Result<Pattern, String> {
+    crate::pattern::load(path).map_err(|e| format!("Failed to load {}: {}", path.display(), e))
+}
+
+fn save_pattern(path: &Path, pattern: &Pattern) -> Result<(), String> {
+    crate::pattern::save(path, pattern)
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
 
-    if args.analyze {
-        let result = crate::analysis::analyze(ruleset);
-        print!("{}", result);
-        return;
+// Create the simulation from the ruleset and pattern options,
+// falling back to the rule of the pattern and the initial state of the ruleset.
+fn create_simulation(args: &SimulationArgs) -> Result<Simulation, String> {
+    let pattern = args.pattern.as_deref().map(load_pattern).transpose()?;
+    let ruleset_name = match (&args.ruleset, &pattern) {
+        (Some(ruleset), _) => ruleset.clone(),
+        (None, Some(pattern)) => {
+            let Some(rule) = &pattern.rule else {
+                return Err("The pattern has no rule, please specify a ruleset".to_owned());
+            };
+            crate::rules::ruleset_from_pattern_rule(rule)
+                .ok_or_else(|| format!("No ruleset matches the pattern rule {}", rule))?
+        }
+        (None, None) => return Err("Please specify a ruleset or a pattern".to_owned()),
+    };
+    let ruleset = crate::rules::create_ruleset(&ruleset_name)
+        .ok_or_else(|| format!("Unknown ruleset {}", ruleset_name))?;
+
+    if let (Some(pattern), Some(path)) = (&pattern, &args.pattern) {
+        if !pattern.fits_states(ruleset.cell_rules.len()) {
+            return Err(format!(
+                "{} has states the ruleset {} doesn't have",
+                path.display(),
+                ruleset_name
+            ));
+        }
     }
 
-    if args.ruleset.as_str() == "byl" {
-        gui_main(ruleset, |sim| {
-            crate::rules::byl::create_initial_state(sim);
-        });
-    } else {
-        gui_main(ruleset, |_| {});
+    let mut sim = Simulation::new(ruleset);
+    if let Some(pattern) = pattern {
+        pattern.place_centered(&mut sim);
+    } else if let Some(initial_state) =
+        crate::rules::find_ruleset(&ruleset_name).and_then(|info| info.initial_state)
+    {
+        initial_state(&mut sim);
+    }
+    Ok(sim)
+}
+
+fn execute(command: Command) -> Result<(), String> {
+    match command {
+        Command::Gui {
+            simulation,
+            library,
+            palette,
+        } => {
+            gui_main(
+                create_simulation(&simulation)?,
+                GuiOptions { library, palette },
+            );
+        }
+        Command::Run {
+            simulation,
+            generations,
+            until_stable,
+            translation_invariant,
+            output,
+            stats,
+        } => {
+            let mut sim = create_simulation(&simulation)?;
+            if stats.is_some() {
+                sim.record_statistics(usize::MAX);
+            }
+            let config = RunConfig {
+                generations,
+                until_stable,
+                translation_invariant,
+            };
+            let result = crate::runner::run(&mut sim, &config);
+            info!("{}", result);
+            let result = Pattern::from_universe(&sim);
+            match output {
+                Some(path) => save_pattern(&path, &result)?,
+                None => print!("{}", crate::pattern::rle::write(&result)),
+            }
+            if let Some(path) = stats {
+                std::fs::write(&path, crate::stats::to_csv(sim.history()))
+                    .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+            }
+        }
+        Command::Analyze {
+            ruleset,
+            analysis,
+            mode: None,
+        } => {
+            // Validated by the argument parser
+            let ruleset = crate::rules::create_ruleset(&ruleset.unwrap()).unwrap();
+            let config = analysis.to_config(ruleset.cell_rules.len())?;
+            let result = crate::analysis::analyze(ruleset, &config);
+            print!("{}", format_report(&result, analysis.soup.output_format));
+        }
+        Command::Analyze {
+            mode: Some(mode), ..
+        } => match mode {
+            AnalyzeMode::Soup { ruleset, soup } => {
+                // Validated by the argument parser
+                let ruleset = crate::rules::create_ruleset(&ruleset).unwrap();
+                let config = soup.to_config(ruleset.cell_rules.len())?;
+                let result = crate::analysis::soup::search(ruleset, &config);
+                print!("{}", format_report(&result, soup.soup.output_format));
+            }
+            AnalyzeMode::Damage { ruleset, damage } => {
+                // Validated by the argument parser
+                let ruleset = crate::rules::create_ruleset(&ruleset).unwrap();
+                let config = damage.to_config(ruleset.cell_rules.len())?;
+                let result = crate::analysis::damage::damage(ruleset, &config);
+                print!("{}", format_report(&result, damage.soup.output_format));
+            }
+            AnalyzeMode::Spatial { ruleset, spatial } => {
+                // Validated by the argument parser
+                let ruleset = crate::rules::create_ruleset(&ruleset).unwrap();
+                let config = spatial.to_config(ruleset.cell_rules.len())?;
+                let result = crate::analysis::spatial::spatial(ruleset, &config);
+                print!("{}", format_report(&result, spatial.soup.output_format));
+            }
+            AnalyzeMode::Sweep { sweep } => {
+                let result = crate::analysis::sweep::sweep(&sweep.to_config()?)?;
+                print!("{}", format_report(&result, sweep.soup.output_format));
+            }
+        },
+        Command::Export { simulation, export } => {
+            let config = export.to_config();
+            let output = export.to_output()?;
+            let mut sim = create_simulation(&simulation)?;
+            let palette = match &export.palette {
+                Some(path) => {
+                    Palette::load_for_ruleset(path, sim.ruleset_name(), sim.get_cell_types_count())?
+                }
+                None => Palette::new(),
+            };
+            crate::export::export(&mut sim, &palette, &config, &output)?;
+        }
+        Command::Convert { input, output } => {
+            let pattern = load_pattern(&input)?;
+            save_pattern(&output, &pattern)?;
+        }
+        Command::Rules {
+            command: RulesCommand::List,
+        } => {
+            for info in crate::rules::RULESETS {
+                let ruleset = (info.create)();
+                println!(
+                    "{}\t{} states\t{}",
+                    info.name,
+                    ruleset.cell_rules.len(),
+                    info.description
+                );
+            }
+        }
+        Command::Bench {
+            ruleset,
+            generations,
+        } => {
+            // Validated by the argument parser
+            let mut sim = Simulation::new(crate::rules::create_ruleset(&ruleset).unwrap());
+            sim.randomize(MIN_COORD, MAX_COORD + 1, MIN_COORD, MAX_COORD + 1);
+            let start = Instant::now();
+            for _ in 0..generations {
+                sim.step();
+            }
+            let elapsed = start.elapsed().as_secs_f64();
+            let side = (MAX_COORD - MIN_COORD + 1) as f64;
+            println!("Generations: {}", generations);
+            println!("Time: {:.3}s", elapsed);
+            println!(
+                "Generations per second: {:.1}",
+                generations as f64 / elapsed
+            );
+            println!(
+                "Cell updates per second: {:.0}",
+                generations as f64 * side * side / elapsed
+            );
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let args = Args::parse();
+
+    if let Err(e) = execute(args.command) {
+        error!("{}", e);
+        return ExitCode::FAILURE;
     }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,192 @@
+use std::path::Path;
+
+// Highest number of states a cell can have
+pub const MAX_STATES: usize = 256;
+
+// Solarized colors used for the first states
+const BASE_COLORS: &[Rgb] = &[
+    Rgb(0x07, 0x36, 0x42),
+    Rgb(0x26, 0x8b, 0xd2),
+    Rgb(0x2a, 0xa1, 0x98),
+    Rgb(0x85, 0x99, 0x00),
+    Rgb(0x6c, 0x71, 0xc4),
+    Rgb(0xd3, 0x36, 0x82),
+    Rgb(0xb5, 0x89, 0x00),
+    Rgb(0xcb, 0x4b, 0x16),
+    Rgb(0xdc, 0x32, 0x2f),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+// Colors of the cell states
+#[derive(Clone, PartialEq, Debug)]
+pub struct Palette {
+    colors: Vec<Rgb>,
+}
+
+impl Palette {
+    // Solarized colors, followed by generated ones for rules with more states
+    pub fn new() -> Self {
+        let colors = (0..MAX_STATES)
+            .map(|state| {
+                BASE_COLORS
+                    .get(state)
+                    .copied()
+                    .unwrap_or_else(|| generated_color(state))
+            })
+            .collect();
+        Palette { colors }
+    }
+
+    pub fn color(&self, cell: u8) -> Rgb {
+        self.colors[cell as usize]
+    }
+
+    // Palette for the ruleset from a palette file, or from the first file
+    // named after the ruleset in a directory (`<name>.colors` or `<name>.rule`,
+    // with the ruleset name or its pattern rule name)
+    pub fn load_for_ruleset(path: &Path, ruleset: &str, states: usize) -> Result<Palette, String> {
+        if !path.is_dir() {
+            return Palette::load(path, states);
+        }
+        let mut names = vec![ruleset.to_owned()];
+        names.extend(crate::rules::pattern_rule_name(ruleset));
+        names
+            .iter()
+            .flat_map(|name| [format!("{}.colors", name), format!("{}.rule", name)])
+            .map(|file| path.join(file))
+            .find(|file| file.is_file())
+            .map_or_else(|| Ok(Palette::new()), |file| Palette::load(&file, states))
+    }
+
+    pub fn load(path: &Path, states: usize) -> Result<Palette, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Palette::parse(&text, states).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Colors in the Golly format, either a whole palette file or the
+    // `@COLORS` section of a `.rule` file. Each line is either
+    // `state r g b`, or `r1 g1 b1 r2 g2 b2` for a gradient over the
+    // non-zero states of a rule with `states` states, optionally preceded
+    // by `color =` or `gradient =` as in `.colors` files. States that aren't
+    // listed keep their default colors.
+    pub fn parse(text: &str, states: usize) -> Result<Palette, String> {
+        let mut palette = Palette::new();
+        let mut lines: Box<dyn Iterator<Item = (usize, &str)>> = Box::new(text.lines().enumerate());
+        if text.lines().any(|line| line.trim().starts_with('@')) {
+            if !text.lines().any(|line| line.trim() == "@COLORS") {
+                return Err("missing @COLORS section".to_owned());
+            }
+            lines = Box::new(
+                lines
+                    .skip_while(|(_, line)| line.trim() != "@COLORS")
+                    .skip(1)
+                    .take_while(|(_, line)| !line.trim().starts_with('@')),
+            );
+        }
+        for (idx, line) in lines {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, line) = match line.split_once('=') {
+                Some((key, values)) => (Some(key.trim()), values),
+                None => (None, line),
+            };
+            let values = line
+                .split_whitespace()
+                .map(|v| v.parse::<u8>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("line {}: expected numbers from 0 to 255", idx + 1))?;
+            match (key, &values[..]) {
+                (Some("color") | None, &[state, r, g, b]) => {
+                    palette.colors[state as usize] = Rgb(r, g, b)
+                }
+                (Some("gradient") | None, &[r1, g1, b1, r2, g2, b2]) => {
+                    let last = states.clamp(2, MAX_STATES) - 1;
+                    for state in 1..=last {
+                        let t = (state - 1) as f32 / (last - 1).max(1) as f32;
+                        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+                        palette.colors[state] = Rgb(mix(r1, r2), mix(g1, g2), mix(b1, b2));
+                    }
+                }
+                (Some("color"), _) => {
+                    return Err(format!("line {}: expected `color = state r g b`", idx + 1))
+                }
+                (Some("gradient"), _) => {
+                    return Err(format!(
+                        "line {}: expected `gradient = r1 g1 b1 r2 g2 b2`",
+                        idx + 1
+                    ))
+                }
+                (Some(key), _) => return Err(format!("line {}: unknown key `{}`", idx + 1, key)),
+                (None, _) => {
+                    return Err(format!(
+                        "line {}: expected `state r g b` or `r1 g1 b1 r2 g2 b2`",
+                        idx + 1
+                    ))
+                }
+            }
+        }
+        Ok(palette)
+    }
+}
+
+// Hues spaced by the golden angle, so that neighboring states differ
+// the most, with brightness cycling to tell apart states of similar hue
+fn generated_color(state: usize) -> Rgb {
+    const GOLDEN_ANGLE: f32 = 137.507_77;
+    let hue = (state as f32 * GOLDEN_ANGLE) % 360.0;
+    let value = [0.9, 0.7, 0.55][state % 3];
+    hsv(hue, 0.75, value)
+}
+
+// Color from a hue in degrees, and saturation and value between 0 and 1
+fn hsv(hue: f32, saturation: f32, value: f32) -> Rgb {
+    let channel = |n: f32| {
+        let k = (n + hue / 60.0) % 6.0;
+        let v = value - value * saturation * k.min(4.0 - k).clamp(0.0, 1.0);
+        (v * 255.0).round() as u8
+    };
+    Rgb(channel(5.0), channel(3.0), channel(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_file() {
+        let text = "# Golly colors\ncolor = 1 255 0 0\ncolor=2 0 255 0\n3 0 0 255\n";
+        let palette = Palette::parse(text, 4).unwrap();
+        assert_eq!(palette.color(1), Rgb(255, 0, 0));
+        assert_eq!(palette.color(2), Rgb(0, 255, 0));
+        assert_eq!(palette.color(3), Rgb(0, 0, 255));
+        assert_eq!(palette.color(0), Palette::new().color(0));
+
+        assert!(Palette::parse("colour = 1 255 0 0", 2).is_err());
+        assert!(Palette::parse("color = 255 0 0", 2).is_err());
+    }
+
+    #[test]
+    fn gradient() {
+        let palette = Palette::parse("gradient = 0 0 0 200 100 0", 3).unwrap();
+        assert_eq!(palette.color(1), Rgb(0, 0, 0));
+        assert_eq!(palette.color(2), Rgb(200, 100, 0));
+        let palette = Palette::parse("0 0 0 200 100 0", 5).unwrap();
+        assert_eq!(palette.color(2), Rgb(66, 33, 0));
+        assert!(Palette::parse("gradient = 1 255 0 0", 2).is_err());
+    }
+
+    #[test]
+    fn rule_file() {
+        let text = "@RULE Test\n@TABLE\n0,1,0\n@COLORS\n1 10 20 30\n@ICONS\n1 1 1 1\n";
+        let palette = Palette::parse(text, 2).unwrap();
+        assert_eq!(palette.color(1), Rgb(10, 20, 30));
+
+        let err = Palette::parse("@RULE Test\n@TABLE\n0,1,0\n", 2).unwrap_err();
+        assert!(err.contains("@COLORS"));
+    }
+}
@@ -0,0 +1,349 @@
+pub mod life105;
+pub mod life106;
+pub mod macrocell;
+pub mod plaintext;
+pub mod rle;
+
+use std::{fmt::Display, path::Path};
+
+use crate::{
+    rules::Cell,
+    sim::Simulation,
+    space::{MAX_COORD, MIN_COORD},
+};
+
+// Largest width or height of a pattern, anything larger could not possibly
+// fit into a simulation
+pub const MAX_SIZE: i64 = MAX_COORD - MIN_COORD + 1;
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+    Unsupported(String),
+    // Wider or taller than MAX_SIZE
+    TooLarge,
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternError::Io(e) => write!(f, "{}", e),
+            PatternError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            PatternError::Unsupported(message) => write!(f, "{}", message),
+            PatternError::TooLarge => write!(
+                f,
+                "Pattern is too large, at most {}x{} cells fit",
+                MAX_SIZE, MAX_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<std::io::Error> for PatternError {
+    fn from(e: std::io::Error) -> Self {
+        PatternError::Io(e)
+    }
+}
+
+pub fn parse_error<T>(line: usize, message: impl Into<String>) -> Result<T, PatternError> {
+    Err(PatternError::Parse {
+        line,
+        message: message.into(),
+    })
+}
+
+pub fn check_two_state(pattern: &Pattern, format: &str) -> Result<(), PatternError> {
+    if pattern.max_state() > 1 {
+        return Err(PatternError::Unsupported(format!(
+            "{} format only supports two-state patterns",
+            format
+        )));
+    }
+    Ok(())
+}
+
+// A rectangular block of cells detached from any simulation.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Pattern {
+    width: i64,
+    height: i64,
+    // Row-major
+    cells: Vec<Cell>,
+    // Rule as written in the pattern file (e.g. B3/S23)
+    pub rule: Option<String>,
+    pub comments: Vec<String>,
+}
+
+impl Pattern {
+    pub fn new(width: i64, height: i64) -> Self {
+        Pattern {
+            width,
+            height,
+            cells: vec![Cell(0); (width * height) as usize],
+            rule: None,
+            comments: vec![],
+        }
+    }
+
+    // Build a pattern from a list of live cells at arbitrary coordinates,
+    // the resulting pattern is trimmed to their bounding box. Fails if they
+    // span more than MAX_SIZE cells.
+    pub fn from_cells(
+        cells: impl IntoIterator<Item = (i64, i64, Cell)>,
+    ) -> Result<Self, PatternError> {
+        let cells = cells
+            .into_iter()
+            .filter(|(_, _, cell)| cell.0 != 0)
+            .collect::<Vec<_>>();
+        if cells.is_empty() {
+            return Ok(Pattern::new(0, 0));
+        }
+        let x_min = cells.iter().map(|c| c.0).min().unwrap();
+        let x_max = cells.iter().map(|c| c.0).max().unwrap();
+        let y_min = cells.iter().map(|c| c.1).min().unwrap();
+        let y_max = cells.iter().map(|c| c.1).max().unwrap();
+        // Far apart cells could overflow the size
+        if x_max.abs_diff(x_min) >= MAX_SIZE as u64 || y_max.abs_diff(y_min) >= MAX_SIZE as u64 {
+            return Err(PatternError::TooLarge);
+        }
+        let mut pattern = Pattern::new(x_max - x_min + 1, y_max - y_min + 1);
+        for (x, y, cell) in cells {
+            pattern.set(x - x_min, y - y_min, cell);
+        }
+        Ok(pattern)
+    }
+
+    // Copy the live cells of the given region, trimmed to their bounding box.
+    // Regions larger than the space only repeat it and are cut down to its size.
+    pub fn from_simulation(
+        sim: &Simulation,
+        x_from: i64,
+        x_to: i64,
+        y_from: i64,
+        y_to: i64,
+    ) -> Self {
+        let mut cells = vec![];
+        for y in y_from..y_to.min(y_from + MAX_SIZE) {
+            for x in x_from..x_to.min(x_from + MAX_SIZE) {
+                cells.push((x, y, sim.get(x, y)));
+            }
+        }
+        Pattern::from_cells(cells).expect("Regions of the space fit into a pattern")
+    }
+
+    // Copy all live cells of the simulation, along with its rule.
+    pub fn from_universe(sim: &Simulation) -> Self {
+        let (x_min, x_max, y_min, y_max) = sim
+            .bounding_box()
+            .unwrap_or((MIN_COORD, MAX_COORD, MIN_COORD, MAX_COORD));
+        let mut pattern = Pattern::from_simulation(sim, x_min, x_max + 1, y_min, y_max + 1);
+        pattern.rule = crate::rules::pattern_rule_name(sim.ruleset_name());
+        pattern
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> Cell {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return Cell(0);
+        }
+        self.cells[(x + y * self.width) as usize]
+    }
+
+    pub fn set(&mut self, x: i64, y: i64, cell: Cell) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        self.cells[(x + y * self.width) as usize] = cell;
+    }
+
+    // Rotated by 90 degrees clockwise
+    pub fn rotated(&self) -> Pattern {
+        let mut result = Pattern::new(self.height, self.width);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                result.set(self.height - 1 - y, x, self.get(x, y));
+            }
+        }
+        result
+    }
+
+    // Mirrored left to right
+    pub fn flipped(&self) -> Pattern {
+        let mut result = Pattern::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                result.set(self.width - 1 - x, y, self.get(x, y));
+            }
+        }
+        result
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|c| c.0 != 0).count()
+    }
+
+    pub fn max_state(&self) -> u8 {
+        self.cells.iter().map(|c| c.0).max().unwrap_or(0)
+    }
+
+    // Whether a ruleset with this many states has all the states of the pattern
+    pub fn fits_states(&self, states: usize) -> bool {
+        (self.max_state() as usize) < states
+    }
+
+    // Put the pattern into the simulation with its top left corner at (x0, y0).
+    pub fn place(&self, sim: &mut Simulation, x0: i64, y0: i64) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                sim.set(x0 + x, y0 + y, self.get(x, y));
+            }
+        }
+    }
+
+    // Put the pattern into the simulation centered around the origin.
+    pub fn place_centered(&self, sim: &mut Simulation) {
+        self.place(sim, -self.width / 2, -self.height / 2);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Rle,
+    Macrocell,
+    Plaintext,
+    Life105,
+    Life106,
+}
+
+impl Format {
+    // Life 1.05 and 1.06 share the extension, None means it has to be detected.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "rle" => Some(Format::Rle),
+            "mc" => Some(Format::Macrocell),
+            "cells" => Some(Format::Plaintext),
+            _ => None,
+        }
+    }
+
+    pub fn detect(text: &str) -> Format {
+        let Some(first_line) = text.lines().map(str::trim).find(|l| !l.is_empty()) else {
+            return Format::Rle;
+        };
+        if first_line.starts_with("[M2]") {
+            Format::Macrocell
+        } else if first_line.starts_with(life105::HEADER) {
+            Format::Life105
+        } else if first_line.starts_with(life106::HEADER) {
+            Format::Life106
+        } else if first_line.starts_with('!') || first_line.chars().all(|c| c == '.' || c == 'O') {
+            Format::Plaintext
+        } else {
+            Format::Rle
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> Result<Pattern, PatternError> {
+        match self {
+            Format::Rle => rle::parse(text),
+            Format::Macrocell => macrocell::parse(text),
+            Format::Plaintext => plaintext::parse(text),
+            Format::Life105 => life105::parse(text),
+            Format::Life106 => life106::parse(text),
+        }
+    }
+
+    pub fn write(&self, pattern: &Pattern) -> Result<String, PatternError> {
+        match self {
+            Format::Rle => Ok(rle::write(pattern)),
+            Format::Macrocell => Ok(macrocell::write(pattern)),
+            Format::Plaintext => plaintext::write(pattern),
+            Format::Life105 => life105::write(pattern),
+            Format::Life106 => life106::write(pattern),
+        }
+    }
+}
+
+pub fn load(path: &Path) -> Result<Pattern, PatternError> {
+    let text = std::fs::read_to_string(path)?;
+    Format::from_path(path)
+        .unwrap_or_else(|| Format::detect(&text))
+        .parse(&text)
+}
+
+// Life 1.06 is used for .lif files as it is the simpler of the two.
+pub fn save(path: &Path, pattern: &Pattern) -> Result<(), PatternError> {
+    let format = match path.extension().and_then(|e| e.to_str()) {
+        Some("lif" | "life") => Format::Life106,
+        _ => Format::from_path(path).unwrap_or(Format::Rle),
+    };
+    std::fs::write(path, format.write(pattern)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        assert_eq!(
+            Format::detect("[M2] (golly 4.2)\n#R B3/S23\n"),
+            Format::Macrocell
+        );
+        assert_eq!(Format::detect("#Life 1.05\n*\n"), Format::Life105);
+        assert_eq!(Format::detect("#Life 1.06\n0 0\n"), Format::Life106);
+        assert_eq!(Format::detect("!Name: Blinker\nOOO\n"), Format::Plaintext);
+        assert_eq!(Format::detect("\n.O.\n"), Format::Plaintext);
+        assert_eq!(
+            Format::detect("#N Blinker\nx = 3, y = 1\n3o!\n"),
+            Format::Rle
+        );
+        assert_eq!(Format::detect("3o!"), Format::Rle);
+        assert_eq!(Format::detect(""), Format::Rle);
+    }
+
+    #[test]
+    fn from_path() {
+        assert_eq!(Format::from_path(Path::new("a.rle")), Some(Format::Rle));
+        assert_eq!(
+            Format::from_path(Path::new("a.MC")),
+            Some(Format::Macrocell)
+        );
+        assert_eq!(
+            Format::from_path(Path::new("dir/a.cells")),
+            Some(Format::Plaintext)
+        );
+        // Either of the Life 1.0x formats
+        assert_eq!(Format::from_path(Path::new("a.lif")), None);
+        assert_eq!(Format::from_path(Path::new("a")), None);
+    }
+
+    #[test]
+    fn from_cells() {
+        let pattern = Pattern::from_cells([(5, -3, Cell(1)), (7, -2, Cell(2)), (0, 0, Cell(0))]);
+        let pattern = pattern.unwrap();
+        assert_eq!((pattern.width(), pattern.height()), (3, 2));
+        assert_eq!(pattern.get(2, 1), Cell(2));
+        assert!(pattern.fits_states(3));
+        assert!(!pattern.fits_states(2));
+
+        let corners = [(0, 0, Cell(1)), (MAX_SIZE - 1, MAX_SIZE - 1, Cell(1))];
+        assert!(Pattern::from_cells(corners).is_ok());
+        let corners = [(0, 0, Cell(1)), (MAX_SIZE, 0, Cell(1))];
+        assert!(matches!(
+            Pattern::from_cells(corners),
+            Err(PatternError::TooLarge)
+        ));
+    }
+}
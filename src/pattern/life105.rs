@@ -0,0 +1,134 @@
+use super::*;
+
+// Life 1.05 format, see https://conwaylife.com/wiki/Life_1.05
+
+pub const HEADER: &str = "#Life 1.05";
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.starts_with(HEADER) => {}
+        _ => return parse_error(1, "Missing Life 1.05 header"),
+    }
+
+    let mut cells = vec![];
+    let mut rule = None;
+    let mut comments = vec![];
+    let (mut x0, mut y) = (0, 0);
+    for (line_idx, line) in lines {
+        let line_no = line_idx + 1;
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix("#D") {
+            comments.push(comment.trim().to_owned());
+        } else if line.starts_with("#N") {
+            rule = Some("B3/S23".to_owned());
+        } else if let Some(r) = line.strip_prefix("#R") {
+            // Rules are written as survival/birth
+            let Some((survival, birth)) = r.trim().split_once('/') else {
+                return parse_error(line_no, format!("Invalid rule: {}", r));
+            };
+            rule = Some(format!("B{}/S{}", birth, survival));
+        } else if let Some(position) = line.strip_prefix("#P") {
+            let coords = position
+                .split_whitespace()
+                .map(|n| n.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>();
+            let Ok(&[x, block_y]) = coords.as_deref() else {
+                return parse_error(line_no, format!("Invalid block position: {}", position));
+            };
+            x0 = x;
+            y = block_y;
+        } else if line.starts_with('#') {
+            continue;
+        } else {
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '.' => {}
+                    '*' => cells.push((x0 + x as i64, y, Cell(1))),
+                    _ => return parse_error(line_no, format!("Unexpected character: {}", c)),
+                }
+            }
+            y += 1;
+        }
+    }
+    let mut pattern = Pattern::from_cells(cells)?;
+    pattern.rule = rule;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> Result<String, PatternError> {
+    check_two_state(pattern, "Life 1.05")?;
+    let mut result = format!("{}\n", HEADER);
+    for comment in pattern.comments.iter() {
+        result += &format!("#D {}\n", comment);
+    }
+    match pattern.rule.as_deref() {
+        Some(rule) if crate::rules::ruleset_from_pattern_rule(rule).as_deref() == Some("life") => {
+            result += "#N\n";
+        }
+        Some(rule) => {
+            let birth_survival = rule
+                .split_once('/')
+                .and_then(|(b, s)| Some((b.strip_prefix('B')?, s.strip_prefix('S')?)));
+            if let Some((birth, survival)) = birth_survival {
+                result += &format!("#R {}/{}\n", survival, birth);
+            }
+        }
+        None => {}
+    }
+    result += &format!("#P {} {}\n", -pattern.width() / 2, -pattern.height() / 2);
+    for y in 0..pattern.height() {
+        let row = (0..pattern.width())
+            .map(|x| if pattern.get(x, y).0 != 0 { '*' } else { '.' })
+            .collect::<String>();
+        // Empty rows still need a character
+        match row.trim_end_matches('.') {
+            "" => result += ".",
+            row => result += row,
+        }
+        result += "\n";
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = "#Life 1.05\n#D A glider\n#N\n#P -1 -1\n.*\n..*\n***\n";
+        let glider = parse(text).unwrap();
+        assert_eq!(glider.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(glider.comments, ["A glider"]);
+        assert_eq!(glider.population(), 5);
+        assert_eq!(parse(&write(&glider).unwrap()).unwrap(), glider);
+
+        // Other rules are written as survival/birth, empty rows with a dot
+        let mut pattern = Pattern::from_cells([(0, 0, Cell(1)), (4, 3, Cell(1))]).unwrap();
+        pattern.rule = Some("B36/S23".to_owned());
+        let text = write(&pattern).unwrap();
+        assert!(text.contains("#R 23/36\n"));
+        assert!(text.contains("\n.\n"));
+        assert_eq!(parse(&text).unwrap(), pattern);
+    }
+
+    #[test]
+    fn blocks() {
+        let text = "#Life 1.05\n#P 0 0\n*\n#P 10 5\n**\n";
+        let pattern = parse(text).unwrap();
+        assert_eq!((pattern.width(), pattern.height()), (12, 6));
+        assert_eq!(pattern.get(11, 5), Cell(1));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("*\n").is_err());
+        assert!(parse("#Life 1.05\n*o\n").is_err());
+        assert!(parse("#Life 1.05\n#P 1\n").is_err());
+        assert!(parse("#Life 1.05\n#R 23\n").is_err());
+        let text = "#Life 1.05\n#P 0 0\n*\n#P 2000000000 0\n*\n";
+        assert!(matches!(parse(text), Err(PatternError::TooLarge)));
+    }
+}
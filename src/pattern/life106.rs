@@ -0,0 +1,75 @@
+use super::*;
+
+// Life 1.06 format, see https://conwaylife.com/wiki/Life_1.06
+
+pub const HEADER: &str = "#Life 1.06";
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.starts_with(HEADER) => {}
+        _ => return parse_error(1, "Missing Life 1.06 header"),
+    }
+
+    let mut cells = vec![];
+    for (line_idx, line) in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let coords = line
+            .split_whitespace()
+            .map(|n| n.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>();
+        let Ok(&[x, y]) = coords.as_deref() else {
+            return parse_error(line_idx + 1, format!("Invalid cell: {}", line));
+        };
+        cells.push((x, y, Cell(1)));
+    }
+    Pattern::from_cells(cells)
+}
+
+pub fn write(pattern: &Pattern) -> Result<String, PatternError> {
+    check_two_state(pattern, "Life 1.06")?;
+    let mut result = format!("{}\n", HEADER);
+    let (x0, y0) = (-pattern.width() / 2, -pattern.height() / 2);
+    for y in 0..pattern.height() {
+        for x in 0..pattern.width() {
+            if pattern.get(x, y).0 != 0 {
+                result += &format!("{} {}\n", x0 + x, y0 + y);
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+        let glider = parse(text).unwrap();
+        assert_eq!((glider.width(), glider.height()), (3, 3));
+        assert_eq!(glider.population(), 5);
+        assert_eq!(parse(&write(&glider).unwrap()).unwrap(), glider);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("0 0\n").is_err());
+        assert!(parse("#Life 1.06\n0\n").is_err());
+        assert!(parse("#Life 1.06\n0 x\n").is_err());
+        let multi_state = Pattern::from_cells([(0, 0, Cell(2))]).unwrap();
+        assert!(write(&multi_state).is_err());
+    }
+
+    #[test]
+    fn too_large() {
+        let text = "#Life 1.06\n0 0\n2000000000 2000000000\n";
+        assert!(matches!(parse(text), Err(PatternError::TooLarge)));
+        let text = format!("#Life 1.06\n{} 0\n{} 0\n", i64::MIN, i64::MAX);
+        assert!(matches!(parse(&text), Err(PatternError::TooLarge)));
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+
+use super::*;
+
+// Golly macrocell format, see https://golly.sourceforge.io/Help/formats.html#mc
+//
+// Nodes are listed bottom-up, each one referring to the previously listed
+// ones by their 1-based index (0 being an empty node of any size). Two-state
+// patterns use 8x8 bitmap leaves of level 3, multi-state patterns list
+// level 1 nodes with the four cell states instead.
+
+const HEADER: &str = "[M2]";
+// Keeps the coordinates of the largest nodes within range
+const MAX_LEVEL: usize = 62;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    // Row-major 8x8 bitmap
+    Leaf8(u64),
+    // nw, ne, sw, se states
+    Leaf1([u8; 4]),
+    // Level and nw, ne, sw, se node indices
+    Inner(u32, [usize; 4]),
+}
+
+impl Node {
+    fn level(&self) -> u32 {
+        match self {
+            Node::Leaf8(_) => 3,
+            Node::Leaf1(_) => 1,
+            Node::Inner(level, _) => *level,
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.starts_with(HEADER) => {}
+        _ => return parse_error(1, "Missing macrocell header"),
+    }
+
+    let mut rule = None;
+    let mut comments = vec![];
+    // Index 0 is the empty node
+    let mut nodes: Vec<Option<Node>> = vec![None];
+    let mut node_lines = vec![0];
+    for (line_idx, line) in lines {
+        let line_no = line_idx + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(r) = line.strip_prefix("#R") {
+            rule = Some(r.trim().to_owned());
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix("#C").or_else(|| line.strip_prefix("#D")) {
+            comments.push(comment.trim().to_owned());
+            continue;
+        }
+        if line.starts_with('#') {
+            // Generation count and other metadata are not preserved
+            continue;
+        }
+
+        let node = if line.starts_with(|c: char| c.is_ascii_digit()) {
+            let numbers = line
+                .split_whitespace()
+                .map(|n| n.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>();
+            let Ok(numbers) = numbers else {
+                return parse_error(line_no, format!("Invalid node: {}", line));
+            };
+            let [level, nw, ne, sw, se] = numbers[..] else {
+                return parse_error(line_no, format!("Invalid node: {}", line));
+            };
+            let children = [nw, ne, sw, se];
+            if level == 0 || level > MAX_LEVEL {
+                return parse_error(line_no, format!("Invalid node level: {}", line));
+            }
+            if level == 1 {
+                if children.iter().any(|c| *c > u8::MAX as usize) {
+                    return parse_error(line_no, format!("State out of range: {}", line));
+                }
+                Node::Leaf1(children.map(|c| c as u8))
+            } else {
+                for child in children {
+                    let child_level = match nodes.get(child) {
+                        Some(Some(node)) => node.level(),
+                        Some(None) => continue,
+                        None => {
+                            return parse_error(line_no, format!("Unknown node: {}", child));
+                        }
+                    };
+                    if child_level + 1 != level as u32 {
+                        return parse_error(line_no, format!("Level mismatch: {}", line));
+                    }
+                }
+                Node::Inner(level as u32, children)
+            }
+        } else {
+            let mut bits = 0u64;
+            let (mut x, mut y) = (0, 0);
+            for c in line.chars() {
+                match c {
+                    '.' => x += 1,
+                    '*' => {
+                        if x >= 8 || y >= 8 {
+                            return parse_error(line_no, "Leaf is larger than 8x8");
+                        }
+                        bits |= 1 << (x + y * 8);
+                        x += 1;
+                    }
+                    '$' => {
+                        x = 0;
+                        y += 1;
+                    }
+                    _ => return parse_error(line_no, format!("Unexpected character: {}", c)),
+                }
+            }
+            Node::Leaf8(bits)
+        };
+        nodes.push(Some(node));
+        node_lines.push(line_no);
+    }
+
+    // Check the size from the tree, expanding a large one could take forever
+    let bounds = node_bounds(&nodes);
+    let mut cells = vec![];
+    if let Some(Some(root)) = nodes.last() {
+        let too_large = |b: &Option<(i64, i64, i64, i64)>| {
+            b.is_some_and(|(x_min, x_max, y_min, y_max)| {
+                x_max - x_min >= MAX_SIZE || y_max - y_min >= MAX_SIZE
+            })
+        };
+        if too_large(bounds.last().unwrap()) {
+            // Blame the first node that doesn't fit, the root only inherits it
+            let idx = bounds.iter().position(too_large).unwrap();
+            return parse_error(node_lines[idx], "Pattern is too large");
+        }
+        let size = 1i64 << root.level();
+        // Golly puts the center of the root node at the origin
+        expand(
+            &nodes,
+            &bounds,
+            nodes.len() - 1,
+            -size / 2,
+            -size / 2,
+            &mut cells,
+        );
+    }
+
+    let mut pattern = Pattern::from_cells(cells)?;
+    pattern.rule = rule;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+// Bounds of the non-zero cells of each node as (x_min, x_max, y_min, y_max),
+// relative to its top left corner, None if it has none. Children are listed
+// before their parents, so their bounds are known by the time they're needed.
+fn node_bounds(nodes: &[Option<Node>]) -> Vec<Option<(i64, i64, i64, i64)>> {
+    let union = |a: Option<(i64, i64, i64, i64)>, b: (i64, i64, i64, i64)| {
+        Some(match a {
+            None => b,
+            Some(a) => (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)),
+        })
+    };
+    let mut bounds: Vec<Option<(i64, i64, i64, i64)>> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let mut node_bounds = None;
+        match node {
+            None => {}
+            Some(Node::Leaf8(bits)) => {
+                for i in (0..64).filter(|i| bits & (1 << i) != 0) {
+                    node_bounds = union(node_bounds, (i % 8, i % 8, i / 8, i / 8));
+                }
+            }
+            Some(Node::Leaf1(states)) => {
+                for i in (0..4).filter(|i| states[*i as usize] != 0) {
+                    node_bounds = union(node_bounds, (i % 2, i % 2, i / 2, i / 2));
+                }
+            }
+            Some(Node::Inner(level, children)) => {
+                let half = 1i64 << (level - 1);
+                for (i, child) in children.iter().enumerate() {
+                    if let Some((x_min, x_max, y_min, y_max)) = bounds[*child] {
+                        let (dx, dy) = (half * (i as i64 % 2), half * (i as i64 / 2));
+                        node_bounds = union(
+                            node_bounds,
+                            (x_min + dx, x_max + dx, y_min + dy, y_max + dy),
+                        );
+                    }
+                }
+            }
+        }
+        bounds.push(node_bounds);
+    }
+    bounds
+}
+
+fn expand(
+    nodes: &[Option<Node>],
+    bounds: &[Option<(i64, i64, i64, i64)>],
+    idx: usize,
+    x0: i64,
+    y0: i64,
+    cells: &mut Vec<(i64, i64, Cell)>,
+) {
+    // Empty nodes can be repeated all over a large root
+    let (Some(node), Some(_)) = (nodes[idx], bounds[idx]) else {
+        return;
+    };
+    match node {
+        Node::Leaf8(bits) => {
+            for i in 0..64 {
+                if bits & (1 << i) != 0 {
+                    cells.push((x0 + i % 8, y0 + i / 8, Cell(1)));
+                }
+            }
+        }
+        Node::Leaf1(states) => {
+            for (i, state) in states.into_iter().enumerate() {
+                cells.push((x0 + i as i64 % 2, y0 + i as i64 / 2, Cell(state)));
+            }
+        }
+        Node::Inner(level, children) => {
+            let half = 1i64 << (level - 1);
+            for (i, child) in children.into_iter().enumerate() {
+                let x = x0 + half * (i as i64 % 2);
+                let y = y0 + half * (i as i64 / 2);
+                expand(nodes, bounds, child, x, y, cells);
+            }
+        }
+    }
+}
+
+struct Writer<'a> {
+    pattern: &'a Pattern,
+    multi_state: bool,
+    indices: HashMap<Node, usize>,
+    lines: Vec<String>,
+}
+
+impl<'a> Writer<'a> {
+    // Returns the index of the node covering the given square, 0 if it is empty.
+    fn node(&mut self, level: u32, x0: i64, y0: i64) -> usize {
+        let node = if self.multi_state && level == 1 {
+            let states = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .map(|(dx, dy)| self.pattern.get(x0 + dx, y0 + dy).0);
+            if states == [0; 4] {
+                return 0;
+            }
+            Node::Leaf1(states)
+        } else if !self.multi_state && level == 3 {
+            let mut bits = 0u64;
+            for i in 0..64 {
+                if self.pattern.get(x0 + i % 8, y0 + i / 8).0 != 0 {
+                    bits |= 1 << i;
+                }
+            }
+            if bits == 0 {
+                return 0;
+            }
+            Node::Leaf8(bits)
+        } else {
+            let half = 1i64 << (level - 1);
+            let children = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .map(|(dx, dy)| self.node(level - 1, x0 + half * dx, y0 + half * dy));
+            if children == [0; 4] {
+                return 0;
+            }
+            Node::Inner(level, children)
+        };
+        if let Some(idx) = self.indices.get(&node) {
+            return *idx;
+        }
+        self.lines.push(node_line(&node));
+        self.indices.insert(node, self.lines.len());
+        self.lines.len()
+    }
+}
+
+fn node_line(node: &Node) -> String {
+    match node {
+        Node::Leaf8(bits) => {
+            let mut rows = (0..8)
+                .map(|y| {
+                    let row = (0..8)
+                        .map(|x| {
+                            if bits & (1 << (x + y * 8)) != 0 {
+                                '*'
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect::<String>();
+                    row.trim_end_matches('.').to_owned()
+                })
+                .collect::<Vec<_>>();
+            while rows.last().is_some_and(|r| r.is_empty()) {
+                rows.pop();
+            }
+            rows.into_iter().map(|r| r + "$").collect()
+        }
+        Node::Leaf1(states) => format!("1 {} {} {} {}", states[0], states[1], states[2], states[3]),
+        Node::Inner(level, children) => format!(
+            "{} {} {} {} {}",
+            level, children[0], children[1], children[2], children[3]
+        ),
+    }
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let multi_state = pattern.max_state() > 1;
+    let mut level = if multi_state { 1 } else { 3 };
+    while (1 << level) < pattern.width().max(pattern.height()) {
+        level += 1;
+    }
+
+    let mut writer = Writer {
+        pattern,
+        multi_state,
+        indices: HashMap::new(),
+        lines: vec![],
+    };
+    // The root has to exist even for an empty pattern
+    if writer.node(level, 0, 0) == 0 {
+        writer
+            .lines
+            .push(node_line(&Node::Inner(level + 1, [0; 4])));
+    }
+
+    let mut result = format!("{} (cellariella v{})\n", HEADER, env!("CARGO_PKG_VERSION"));
+    if let Some(rule) = &pattern.rule {
+        result += &format!("#R {}\n", rule);
+    }
+    for comment in pattern.comments.iter() {
+        result += &format!("#C {}\n", comment);
+    }
+    for line in writer.lines {
+        result += &line;
+        result += "\n";
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut glider = Pattern::from_cells([
+            (1, 0, Cell(1)),
+            (2, 1, Cell(1)),
+            (0, 2, Cell(1)),
+            (1, 2, Cell(1)),
+            (2, 2, Cell(1)),
+        ])
+        .unwrap();
+        glider.rule = Some("B3/S23".to_owned());
+        glider.comments = vec!["Glider".to_owned()];
+        assert_eq!(parse(&write(&glider)).unwrap(), glider);
+
+        let multi_state =
+            Pattern::from_cells([(0, 0, Cell(3)), (5, 2, Cell(1)), (9, 9, Cell(200))]).unwrap();
+        assert_eq!(parse(&write(&multi_state)).unwrap(), multi_state);
+
+        let empty = Pattern::new(0, 0);
+        assert_eq!(parse(&write(&empty)).unwrap(), empty);
+    }
+
+    #[test]
+    fn small_pattern_in_large_root() {
+        let mut text = "[M2]\n.*$\n4 1 0 0 0\n".to_owned();
+        for level in 5..=40 {
+            text += &format!("{} {} 0 0 0\n", level, level - 3);
+        }
+        assert_eq!(parse(&text).unwrap().population(), 1);
+    }
+
+    #[test]
+    fn too_large() {
+        // Each level repeats the previous one four times, 2^24 cells at level 12
+        let mut text =
+            "[M2]\n********$********$********$********$********$********$********$********$\n"
+                .to_owned();
+        for level in 4..=12 {
+            let child = level - 3;
+            text += &format!("{} {} {} {} {}\n", level, child, child, child, child);
+        }
+        // The level 9 node on line 8 is the first one larger than the space
+        assert!(matches!(
+            parse(&text),
+            Err(PatternError::Parse { line: 8, .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_level() {
+        assert!(parse("[M2]\n.*$\n64 1 0 0 0\n").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use super::*;
+
+// Plaintext format, see https://conwaylife.com/wiki/Plaintext
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut cells = vec![];
+    let mut comments = vec![];
+    let mut y = 0;
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            comments.push(comment.trim().to_owned());
+            continue;
+        }
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' | '*' => cells.push((x as i64, y, Cell(1))),
+                _ => {
+                    return parse_error(line_idx + 1, format!("Unexpected character: {}", c));
+                }
+            }
+        }
+        y += 1;
+    }
+    let mut pattern = Pattern::from_cells(cells)?;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> Result<String, PatternError> {
+    check_two_state(pattern, "Plaintext")?;
+    let mut result = String::new();
+    for comment in pattern.comments.iter() {
+        result += &format!("!{}\n", comment);
+    }
+    for y in 0..pattern.height() {
+        let row = (0..pattern.width())
+            .map(|x| if pattern.get(x, y).0 != 0 { 'O' } else { '.' })
+            .collect::<String>();
+        result += row.trim_end_matches('.');
+        result += "\n";
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = "!Name: Glider\n.O\n..O\nOOO\n";
+        let glider = parse(text).unwrap();
+        assert_eq!(glider.comments, ["Name: Glider"]);
+        assert_eq!(glider.population(), 5);
+        assert_eq!(write(&glider).unwrap(), text);
+        assert_eq!(parse(&write(&glider).unwrap()).unwrap(), glider);
+
+        // Stars are accepted too, empty rows are kept
+        let pattern = parse("*\n\n..*").unwrap();
+        assert_eq!((pattern.width(), pattern.height()), (3, 3));
+        assert_eq!(parse(&write(&pattern).unwrap()).unwrap(), pattern);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse(".O\nx = 1\n").is_err());
+        let multi_state = Pattern::from_cells([(0, 0, Cell(2))]).unwrap();
+        assert!(write(&multi_state).is_err());
+        let text = format!("O{}O\n", ".".repeat(300));
+        assert!(matches!(parse(&text), Err(PatternError::TooLarge)));
+    }
+}
@@ -0,0 +1,246 @@
+use super::*;
+
+// Run Length Encoded format, see https://conwaylife.com/wiki/Run_Length_Encoded
+
+const MAX_LINE_LENGTH: usize = 70;
+// No more cells than the space has, long runs could otherwise list billions
+const MAX_CELLS: usize = (MAX_SIZE * MAX_SIZE) as usize;
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut cells = vec![];
+    let mut rule = None;
+    let mut comments = vec![];
+    let mut header_seen = false;
+    let (mut x, mut y) = (0i64, 0i64);
+    'lines: for (line_idx, line) in text.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix("#C").or_else(|| line.strip_prefix("#c")) {
+            comments.push(comment.trim().to_owned());
+            continue;
+        }
+        if line.starts_with('#') {
+            // Name, author and offset lines are not preserved
+            continue;
+        }
+        if !header_seen && line.starts_with('x') {
+            header_seen = true;
+            let mut items = line;
+            while !items.is_empty() {
+                let (item, rest) = items.split_once(',').unwrap_or((items, ""));
+                let Some((key, _)) = item.split_once('=') else {
+                    return parse_error(line_no, format!("Invalid header item: {}", item));
+                };
+                match key.trim() {
+                    // The size is recomputed from the actual contents
+                    "x" | "y" => {}
+                    // The rule is last and can contain commas, as in the
+                    // bounded grid of B3/S23:T100,100
+                    "rule" => {
+                        let (_, rule_text) = items.split_once('=').unwrap_or_default();
+                        rule = Some(rule_text.trim().to_owned());
+                        break;
+                    }
+                    _ => {}
+                }
+                items = rest;
+            }
+            continue;
+        }
+
+        let mut chars = line.chars().peekable();
+        let mut count = None::<i64>;
+        while let Some(c) = chars.next() {
+            let state = match c {
+                '0'..='9' => {
+                    let digit = c as i64 - '0' as i64;
+                    count = count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(digit));
+                    if count.is_none() {
+                        return parse_error(line_no, "Run count is too large");
+                    }
+                    continue;
+                }
+                '!' => break 'lines,
+                '$' => {
+                    let Some(next_y) = y.checked_add(count.take().unwrap_or(1)) else {
+                        return parse_error(line_no, "Pattern is too large");
+                    };
+                    y = next_y;
+                    x = 0;
+                    continue;
+                }
+                c if c.is_whitespace() => continue,
+                'b' | '.' => 0,
+                'o' => 1,
+                'A'..='X' => c as u32 - 'A' as u32 + 1,
+                'p'..='y' if chars.peek().is_some_and(|n| n.is_ascii_uppercase()) => {
+                    let letter = chars.next().unwrap();
+                    if !('A'..='X').contains(&letter) {
+                        return parse_error(line_no, format!("Invalid state: {}{}", c, letter));
+                    }
+                    (c as u32 - 'p' as u32 + 1) * 24 + letter as u32 - 'A' as u32 + 1
+                }
+                // Some writers use arbitrary letters for live cells in two-state patterns
+                c if c.is_ascii_lowercase() => 1,
+                _ => return parse_error(line_no, format!("Unexpected character: {}", c)),
+            };
+            if state > u8::MAX as u32 {
+                return parse_error(line_no, format!("State {} is out of range", state));
+            }
+            let run = count.take().unwrap_or(1);
+            if state != 0 {
+                if run > MAX_SIZE || cells.len() + run as usize > MAX_CELLS {
+                    return parse_error(line_no, "Pattern is too large");
+                }
+                cells.extend((0..run).map(|i| (x + i, y, Cell(state as u8))));
+            }
+            let Some(next_x) = x.checked_add(run) else {
+                return parse_error(line_no, "Pattern is too large");
+            };
+            x = next_x;
+        }
+        if count.is_some() {
+            return parse_error(line_no, "Run count without a cell state");
+        }
+    }
+    let mut pattern = Pattern::from_cells(cells)?;
+    pattern.rule = rule;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+// Whether the first line after any comments is the `x = .., y = ..` header
+pub fn has_header(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .and_then(|line| line.strip_prefix('x'))
+        .is_some_and(|rest| rest.trim_start().starts_with('='))
+}
+
+fn state_tag(state: u8, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_owned(),
+        (_, false) => "o".to_owned(),
+        (0, true) => ".".to_owned(),
+        (1..=24, true) => ((b'A' + state - 1) as char).to_string(),
+        _ => {
+            let prefix = (b'p' + (state - 1) / 24 - 1) as char;
+            let letter = (b'A' + (state - 1) % 24) as char;
+            format!("{}{}", prefix, letter)
+        }
+    }
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let multi_state = pattern.max_state() > 1;
+
+    let mut tokens = vec![];
+    let push_run = |tokens: &mut Vec<String>, run: i64, tag: &str| {
+        if run == 1 {
+            tokens.push(tag.to_owned());
+        } else {
+            tokens.push(format!("{}{}", run, tag));
+        }
+    };
+    let mut current_row = 0;
+    for y in 0..pattern.height() {
+        // Trailing dead cells of a row are implied
+        let Some(row_end) = (0..pattern.width())
+            .rev()
+            .find(|x| pattern.get(*x, y).0 != 0)
+        else {
+            continue;
+        };
+        if y > current_row {
+            push_run(&mut tokens, y - current_row, "$");
+            current_row = y;
+        }
+        let mut x = 0;
+        while x <= row_end {
+            let cell = pattern.get(x, y);
+            let mut run = 1;
+            while x + run <= row_end && pattern.get(x + run, y) == cell {
+                run += 1;
+            }
+            push_run(&mut tokens, run, &state_tag(cell.0, multi_state));
+            x += run;
+        }
+    }
+    tokens.push("!".to_owned());
+
+    let mut result = String::new();
+    for comment in pattern.comments.iter() {
+        result += &format!("#C {}\n", comment);
+    }
+    result += &format!("x = {}, y = {}", pattern.width(), pattern.height());
+    if let Some(rule) = &pattern.rule {
+        result += &format!(", rule = {}", rule);
+    }
+    result += "\n";
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length + token.len() > MAX_LINE_LENGTH {
+            result += "\n";
+            line_length = 0;
+        }
+        line_length += token.len();
+        result += &token;
+    }
+    result += "\n";
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_grid_rule() {
+        let pattern = parse("x = 3, y = 3, rule = B3/S23:T100,100\nbo$2bo$3o!\n").unwrap();
+        assert_eq!(pattern.rule.as_deref(), Some("B3/S23:T100,100"));
+        assert_eq!(pattern.population(), 5);
+    }
+
+    #[test]
+    fn too_large() {
+        assert!(parse("o999999999$o!").is_err());
+        assert!(parse("o$999999999bo!").is_err());
+        assert!(parse("999999999o!").is_err());
+        assert!(parse("99999999999999999999999o!").is_err());
+        assert!(parse(&"256o$".repeat(300)).is_err());
+        assert_eq!(parse("256o!").unwrap().width(), 256);
+    }
+
+    #[test]
+    fn header() {
+        assert!(has_header("x = 3, y = 1\n3o!"));
+        assert!(has_header("#N Blinker\n\n  x=3,y=1\n3o!"));
+        assert!(!has_header("3o!"));
+        assert!(!has_header("xo$ox!"));
+        assert!(!has_header("hello"));
+        assert!(!has_header(""));
+    }
+
+    #[test]
+    fn invalid_header_item() {
+        assert!(parse("x = 3, y\n3o!\n").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "#C A comment\nx = 4, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+        let pattern = parse(text).unwrap();
+        assert_eq!(parse(&write(&pattern)).unwrap(), pattern);
+
+        let multi_state =
+            Pattern::from_cells([(0, 0, Cell(1)), (2, 0, Cell(30)), (1, 2, Cell(255))]).unwrap();
+        assert_eq!(parse(&write(&multi_state)).unwrap(), multi_state);
+    }
+}
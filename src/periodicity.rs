@@ -0,0 +1,150 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::sim::Simulation;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Periodicity {
+    // First step of the cycle
+    pub start: u64,
+    pub period: u64,
+    // Displacement over one period, non-zero for spaceships
+    pub dx: i64,
+    pub dy: i64,
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Periodicity {
+    pub fn is_spaceship(&self) -> bool {
+        self.dx != 0 || self.dy != 0
+    }
+
+    // Speed in the usual notation, e.g. c/4 diagonal or 2c/7 orthogonal
+    pub fn speed(&self) -> String {
+        let (adx, ady) = (self.dx.unsigned_abs(), self.dy.unsigned_abs());
+        let distance = adx.max(ady);
+        let divisor = gcd(distance, self.period);
+        let (distance, period) = (distance / divisor, self.period / divisor);
+        let c = if distance == 1 {
+            "c".to_owned()
+        } else {
+            format!("{}c", distance)
+        };
+        if adx == 0 || ady == 0 {
+            format!("{}/{} orthogonal", c, period)
+        } else if adx == ady {
+            format!("{}/{} diagonal", c, period)
+        } else {
+            // The displacement already gives the distance, e.g. (2,1)c/6
+            format!("({},{})c/{} oblique", adx, ady, self.period)
+        }
+    }
+}
+
+impl Display for Periodicity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_spaceship() {
+            write!(
+                f,
+                "spaceship from step {} with period {}, displacement ({}, {}), speed {}",
+                self.start,
+                self.period,
+                self.dx,
+                self.dy,
+                self.speed()
+            )
+        } else if self.period == 1 {
+            write!(f, "stabilized at step {}", self.start)
+        } else {
+            write!(
+                f,
+                "stabilized at step {} with period {}",
+                self.start, self.period
+            )
+        }
+    }
+}
+
+// Detects a repeated state by remembering the hashes of all observed steps.
+// A hash collision could produce a false positive, but with 64-bit hashes
+// this is unlikely enough to be ignored.
+pub struct PeriodDetector {
+    // Treat copies of a state shifted in space as the same state
+    translation_invariant: bool,
+    // Step and position of the bounding box for each observed hash
+    seen: HashMap<u64, (u64, i64, i64)>,
+}
+
+impl PeriodDetector {
+    pub fn new(translation_invariant: bool) -> Self {
+        PeriodDetector {
+            translation_invariant,
+            seen: HashMap::new(),
+        }
+    }
+
+    // Forget the observed steps, e.g. after the simulation was edited
+    pub fn reset(&mut self) {
+        self.seen.clear();
+    }
+
+    // Observe the current step, returns the cycle once a step repeats.
+    pub fn observe(&mut self, sim: &Simulation) -> Option<Periodicity> {
+        let (hash, x, y) = if self.translation_invariant {
+            sim.normalized_hash()
+        } else {
+            (sim.hash(), 0, 0)
+        };
+        let step = sim.current_step();
+        match self.seen.insert(hash, (step, x, y)) {
+            Some((start, start_x, start_y)) if start < step => Some(Periodicity {
+                start,
+                period: step - start,
+                dx: x - start_x,
+                dy: y - start_y,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speed(period: u64, dx: i64, dy: i64) -> String {
+        Periodicity {
+            start: 0,
+            period,
+            dx,
+            dy,
+        }
+        .speed()
+    }
+
+    #[test]
+    fn orthogonal_speed() {
+        assert_eq!(speed(4, 2, 0), "c/2 orthogonal");
+        assert_eq!(speed(4, 0, -1), "c/4 orthogonal");
+        assert_eq!(speed(7, -2, 0), "2c/7 orthogonal");
+    }
+
+    #[test]
+    fn diagonal_speed() {
+        assert_eq!(speed(4, 1, 1), "c/4 diagonal");
+        assert_eq!(speed(8, -2, 2), "c/4 diagonal");
+        assert_eq!(speed(5, 2, -2), "2c/5 diagonal");
+    }
+
+    #[test]
+    fn oblique_speed() {
+        assert_eq!(speed(6, 2, 1), "(2,1)c/6 oblique");
+        assert_eq!(speed(6, -1, 2), "(1,2)c/6 oblique");
+    }
+}
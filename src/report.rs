@@ -0,0 +1,161 @@
+use std::{
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Helpers for writing results in machine-readable formats
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+// Results that can be written in any of the output formats
+pub trait Report: Display {
+    fn to_json(&self) -> String;
+    fn to_csv(&self) -> String;
+}
+
+pub fn format_report(report: &impl Report, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text => report.to_string(),
+        OutputFormat::Json => report.to_json(),
+        OutputFormat::Csv => report.to_csv(),
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => result += "\\\"",
+            '\\' => result += "\\\\",
+            '\n' => result += "\\n",
+            '\r' => result += "\\r",
+            '\t' => result += "\\t",
+            c if (c as u32) < 0x20 => result += &format!("\\u{:04x}", c as u32),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+// JSON has no representation for NaN and infinities
+pub fn json_number<T: Into<f64> + Display + Copy>(n: T) -> String {
+    if n.into().is_finite() {
+        n.to_string()
+    } else {
+        "null".to_owned()
+    }
+}
+
+pub fn json_array<T>(items: &[T], f: impl Fn(&T) -> String) -> String {
+    format!("[{}]", items.iter().map(f).collect::<Vec<_>>().join(","))
+}
+
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+// ISO 8601 in UTC, e.g. 2023-07-14T12:34:56Z
+pub fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, secs_of_day) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    struct Count(usize);
+
+    impl Display for Count {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Count: {}", self.0)
+        }
+    }
+
+    impl Report for Count {
+        fn to_json(&self) -> String {
+            format!("{{\"count\":{}}}", self.0)
+        }
+
+        fn to_csv(&self) -> String {
+            format!("count\n{}\n", self.0)
+        }
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(format_report(&Count(3), OutputFormat::Text), "Count: 3");
+        assert_eq!(
+            format_report(&Count(3), OutputFormat::Json),
+            "{\"count\":3}"
+        );
+        assert_eq!(format_report(&Count(3), OutputFormat::Csv), "count\n3\n");
+    }
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(
+            json_string("a\"b\\c\nd\re\tf\u{1}g\u{e9}"),
+            "\"a\\\"b\\\\c\\nd\\re\\tf\\u0001g\u{e9}\""
+        );
+        assert_eq!(json_number(1.5f32), "1.5");
+        assert_eq!(json_number(f32::NAN), "null");
+        assert_eq!(json_number(f64::INFINITY), "null");
+        assert_eq!(json_array(&[1, 2, 3], |n| n.to_string()), "[1,2,3]");
+        assert_eq!(json_array(&[] as &[u8], |n| n.to_string()), "[]");
+    }
+
+    #[test]
+    fn csv_escaping() {
+        assert_eq!(csv_field("B3/S23"), "B3/S23");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn timestamps() {
+        let at = |secs: u64| format_timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(1_689_338_096), "2023-07-14T12:34:56Z");
+        assert_eq!(at(951_868_799), "2000-02-29T23:59:59Z");
+        // Times before the epoch are clamped to it
+        let before = UNIX_EPOCH - Duration::from_secs(10);
+        assert_eq!(format_timestamp(before), "1970-01-01T00:00:00Z");
+    }
+}
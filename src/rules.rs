@@ -1,15 +1,24 @@
+pub mod brain;
 pub mod byl;
 pub mod life;
+pub mod totalistic;
 pub mod wireworld;
 
-use crate::space::{Neighborhood, Space};
+use std::sync::OnceLock;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+use crate::{
+    sim::Simulation,
+    space::{Neighborhood, Space, MAX_COORD, MIN_COORD},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Cell(pub u8);
 
 #[derive(Clone, Debug)]
 pub enum RuleCondition {
     CountBetween(Cell, u8, u8),
+    // Bit N of the mask is set if N cells are allowed
+    CountIn(Cell, u16),
 }
 
 impl RuleCondition {
@@ -19,6 +28,10 @@ impl RuleCondition {
                 let count = space.count(x, y, *cell, neighborhood);
                 count >= *from && count <= *to
             }
+            RuleCondition::CountIn(cell, mask) => {
+                let count = space.count(x, y, *cell, neighborhood);
+                mask & (1 << count) != 0
+            }
         }
     }
 }
@@ -33,6 +46,9 @@ pub enum Rule {
         fn(i64, i64, Cell, &Space, Neighborhood) -> Option<Cell>,
         Box<Rule>,
     ),
+    // New cell indexed by the sum of the states in the neighborhood,
+    // unchanged if the table is too short
+    SumTable(Vec<Cell>),
 }
 
 impl Rule {
@@ -57,28 +73,96 @@ impl Rule {
             Rule::Custom(f) => f(x, y, cell, space, neighborhood),
             Rule::TryCustom(f, default) => f(x, y, cell, space, neighborhood)
                 .unwrap_or_else(|| default.apply(x, y, cell, space, neighborhood)),
+            Rule::SumTable(table) => {
+                let mut sum = 0;
+                space.iterate_neighbors(neighborhood, x, y, |x, y| {
+                    sum += space.get(x, y).0 as usize;
+                });
+                table.get(sum).copied().unwrap_or(cell)
+            }
+        }
+    }
+
+    // Whether applying the rule ends up in a default rather than an explicit
+    // transition, e.g. a TryCustom table missing the neighborhood
+    pub fn falls_through(
+        &self,
+        x: i64,
+        y: i64,
+        cell: Cell,
+        space: &Space,
+        neighborhood: Neighborhood,
+    ) -> bool {
+        match self {
+            Rule::Static | Rule::Transition(_) | Rule::Custom(_) => false,
+            Rule::Conditional(condition, rule, otherwise) => {
+                if condition.test(x, y, space, neighborhood) {
+                    rule.falls_through(x, y, cell, space, neighborhood)
+                } else {
+                    otherwise.falls_through(x, y, cell, space, neighborhood)
+                }
+            }
+            Rule::TryCustom(f, _) => f(x, y, cell, space, neighborhood).is_none(),
+            Rule::SumTable(table) => {
+                let mut sum = 0;
+                space.iterate_neighbors(neighborhood, x, y, |x, y| {
+                    sum += space.get(x, y).0 as usize;
+                });
+                sum >= table.len()
+            }
         }
     }
 }
 
+// Space of zero cells to probe rules with, allocated once as it is never
+// written to
+fn zero_space() -> &'static Space {
+    static ZERO_SPACE: OnceLock<Space> = OnceLock::new();
+    ZERO_SPACE.get_or_init(Space::new)
+}
+
 #[derive(Clone)]
 pub struct RuleSet {
+    pub name: String,
     pub cell_rules: Vec<Rule>,
-    neighborhood: Neighborhood,
+    pub neighborhood: Neighborhood,
+    // Zero cells surrounded by zero cells stay zero, so only the
+    // surroundings of non-zero cells need to be updated
+    quiescent: bool,
 }
 
 impl RuleSet {
-    pub fn new(neighborhood: Neighborhood, rules: &[Rule]) -> Self {
+    pub fn new(name: &str, neighborhood: Neighborhood, rules: &[Rule]) -> Self {
+        let quiescent = rules[0].apply(0, 0, Cell(0), zero_space(), neighborhood) == Cell(0);
         RuleSet {
+            name: name.to_owned(),
             cell_rules: rules.to_vec(),
             neighborhood,
+            quiescent,
         }
     }
 
     pub fn apply(&self, space: &mut Space) {
-        // TODO: take space size and cells into account
-        for x in -128..128 {
-            for y in -128..128 {
+        let full = (MIN_COORD, MAX_COORD);
+        let (x_range, y_range) = if self.quiescent {
+            space.clear_new();
+            let Some((x_min, x_max, y_min, y_max)) = space.bounding_box() else {
+                return;
+            };
+            // Cells next to the non-zero ones can change as well
+            let expand = |from: i64, to: i64| {
+                if to - from + 3 > MAX_COORD - MIN_COORD + 1 {
+                    full
+                } else {
+                    (from - 1, to + 1)
+                }
+            };
+            (expand(x_min, x_max), expand(y_min, y_max))
+        } else {
+            (full, full)
+        };
+        for x in x_range.0..=x_range.1 {
+            for y in y_range.0..=y_range.1 {
                 let old_cell = space.get(x, y);
                 let new_cell = self.cell_rules[old_cell.0 as usize].apply(
                     x,
@@ -93,11 +177,151 @@ impl RuleSet {
     }
 }
 
+pub struct RuleSetInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub create: fn() -> RuleSet,
+    // Pattern to start from when no pattern file is given
+    pub initial_state: Option<fn(&mut Simulation)>,
+    // Rule names used by pattern files (following Golly),
+    // the first one is used when writing patterns
+    pub pattern_rules: &'static [&'static str],
+}
+
+pub const RULESETS: &[RuleSetInfo] = &[
+    RuleSetInfo {
+        name: "brain",
+        description: "Brian's Brain",
+        create: self::brain::brain,
+        initial_state: None,
+        pattern_rules: &["BriansBrain", "Brain", "/2/3"],
+    },
+    RuleSetInfo {
+        name: "byl",
+        description: "Byl's self-replicating loop",
+        create: self::byl::byl,
+        initial_state: Some(self::byl::create_initial_state),
+        pattern_rules: &["Byl-Loop", "Byl"],
+    },
+    RuleSetInfo {
+        name: "life",
+        description: "Conway's Game of Life",
+        create: self::life::life,
+        initial_state: None,
+        pattern_rules: &["B3/S23", "23/3", "Life", "Conway"],
+    },
+    RuleSetInfo {
+        name: "wireworld",
+        description: "Wireworld electronic circuits",
+        create: self::wireworld::wireworld,
+        initial_state: None,
+        pattern_rules: &["WireWorld"],
+    },
+];
+
+pub fn find_ruleset(name: &str) -> Option<&'static RuleSetInfo> {
+    RULESETS.iter().find(|info| info.name == name)
+}
+
+// Named rulesets, along with rule families written out in full
+// (e.g. B36/S23, see life::life_like and totalistic::totalistic)
 pub fn create_ruleset(ruleset: &str) -> Option<RuleSet> {
-    match ruleset {
-        "byl" => Some(self::byl::byl()),
-        "life" => Some(self::life::life()),
-        "wireworld" => Some(self::wireworld::wireworld()),
-        _ => None,
+    find_ruleset(ruleset)
+        .map(|info| (info.create)())
+        .or_else(|| self::life::parse_life_like(ruleset))
+        .or_else(|| self::totalistic::parse_totalistic(ruleset))
+}
+
+fn normalize_rule_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+pub fn ruleset_from_pattern_rule(rule: &str) -> Option<String> {
+    let normalized = normalize_rule_name(rule);
+    RULESETS
+        .iter()
+        .find(|info| {
+            info.pattern_rules
+                .iter()
+                .any(|n| normalize_rule_name(n) == normalized)
+        })
+        .map(|info| info.name.to_owned())
+        .or_else(|| self::life::parse_life_like(rule).map(|ruleset| ruleset.name))
+}
+
+pub fn pattern_rule_name(ruleset: &str) -> Option<String> {
+    match find_ruleset(ruleset) {
+        Some(info) => Some(info.pattern_rules[0].to_owned()),
+        // Life-like rules are named the way pattern files expect
+        None => self::life::parse_life_like(ruleset).map(|ruleset| ruleset.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pseudo-random cells in the middle of the space
+    fn soup(space: &mut Space, states: u8) {
+        let mut seed = 12345u64;
+        for y in -20..20 {
+            for x in -20..20 {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                space.set_curr(x, y, Cell((seed >> 33) as u8 % states));
+            }
+        }
+    }
+
+    // Update of every cell of the space, regardless of the rule
+    fn full_update(ruleset: &RuleSet, space: &mut Space) {
+        let mut full = ruleset.clone();
+        full.quiescent = false;
+        full.apply(space);
+        space.finalize_step();
+    }
+
+    fn assert_same_as_full_update(ruleset: &RuleSet, states: u8) {
+        let mut space = Space::new();
+        soup(&mut space, states);
+        let mut expected = space.clone();
+        for _ in 0..20 {
+            ruleset.apply(&mut space);
+            space.finalize_step();
+            full_update(ruleset, &mut expected);
+            assert_eq!(space.hash(), expected.hash());
+            for y in MIN_COORD..=MAX_COORD {
+                for x in MIN_COORD..=MAX_COORD {
+                    assert_eq!(space.get(x, y), expected.get(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quiescent_rules() {
+        let life = create_ruleset("life").unwrap();
+        assert!(life.quiescent);
+        assert_same_as_full_update(&life, 2);
+        let brain = create_ruleset("brain").unwrap();
+        assert!(brain.quiescent);
+        assert_same_as_full_update(&brain, 3);
+    }
+
+    #[test]
+    fn non_quiescent_rule() {
+        let b0 = create_ruleset("B01/S23").unwrap();
+        assert!(!b0.quiescent);
+        assert_same_as_full_update(&b0, 2);
+
+        // Empty cells far away from anything are born as well
+        let mut space = Space::new();
+        b0.apply(&mut space);
+        space.finalize_step();
+        assert_eq!(space.get(MIN_COORD, MAX_COORD), Cell(1));
     }
 }
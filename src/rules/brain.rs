@@ -0,0 +1,19 @@
+use super::*;
+
+// Dead cells with exactly two firing neighbors fire, firing cells become
+// refractory and refractory cells die
+pub fn brain() -> RuleSet {
+    RuleSet::new(
+        "brain",
+        Neighborhood::Moore,
+        &[
+            Rule::Conditional(
+                RuleCondition::CountBetween(Cell(1), 2, 2),
+                Box::new(Rule::Transition(Cell(1))),
+                Box::new(Rule::Static),
+            ),
+            Rule::Transition(Cell(2)),
+            Rule::Transition(Cell(0)),
+        ],
+    )
+}
@@ -1,11 +1,10 @@
-use crate::sim::Simulation;
-
 use super::*;
 
 // Implementation based on https://www.asa3.org/ASA/PSCF/1989/PSCF3-89Byl.html
 
 pub fn byl() -> RuleSet {
     RuleSet::new(
+        "byl",
         Neighborhood::VonNeumann,
         &[
             Rule::TryCustom(byl_transition_table, Box::new(Rule::Static)),
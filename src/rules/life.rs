@@ -2,6 +2,7 @@ use super::*;
 
 pub fn life() -> RuleSet {
     RuleSet::new(
+        "life",
         Neighborhood::Moore,
         &[
             Rule::Conditional(
@@ -17,3 +18,52 @@ pub fn life() -> RuleSet {
         ],
     )
 }
+
+// Name of a life-like rule in the B/S notation, e.g. B36/S23
+pub fn life_like_name(birth: u16, survival: u16) -> String {
+    let counts = |mask: u16| {
+        (0..=8)
+            .filter(|n| mask & (1 << n) != 0)
+            .map(|n: u8| n.to_string())
+            .collect::<String>()
+    };
+    format!("B{}/S{}", counts(birth), counts(survival))
+}
+
+// Outer totalistic rule with births and survivals for the neighbor counts
+// set in the masks (bit N for N neighbors)
+pub fn life_like(birth: u16, survival: u16) -> RuleSet {
+    RuleSet::new(
+        &life_like_name(birth, survival),
+        Neighborhood::Moore,
+        &[
+            Rule::Conditional(
+                RuleCondition::CountIn(Cell(1), birth),
+                Box::new(Rule::Transition(Cell(1))),
+                Box::new(Rule::Static),
+            ),
+            // The count includes the cell itself
+            Rule::Conditional(
+                RuleCondition::CountIn(Cell(1), survival << 1),
+                Box::new(Rule::Static),
+                Box::new(Rule::Transition(Cell(0))),
+            ),
+        ],
+    )
+}
+
+// Parse a rule in the B/S notation, e.g. B3/S23
+pub fn parse_life_like(name: &str) -> Option<RuleSet> {
+    let (birth, survival) = name.split_once('/')?;
+    let birth = birth.strip_prefix(['B', 'b'])?;
+    let survival = survival.strip_prefix(['S', 's'])?;
+    let mask = |counts: &str| {
+        counts
+            .chars()
+            .try_fold(0u16, |mask, c| match c.to_digit(10) {
+                Some(n) if n <= 8 => Some(mask | 1 << n),
+                _ => None,
+            })
+    };
+    Some(life_like(mask(birth)?, mask(survival)?))
+}
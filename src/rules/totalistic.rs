@@ -0,0 +1,58 @@
+use super::*;
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+// Totalistic rule on the sum of the states in the Moore neighborhood
+// (including the cell itself), with one table per state. Named like
+// T3:<table of state 0>/<table of state 1>/..., one digit per sum.
+pub fn totalistic(tables: &[Vec<Cell>]) -> RuleSet {
+    let name = tables
+        .iter()
+        .map(|table| {
+            table
+                .iter()
+                .map(|cell| DIGITS[cell.0 as usize] as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    let rules = tables
+        .iter()
+        .map(|table| Rule::SumTable(table.clone()))
+        .collect::<Vec<_>>();
+    RuleSet::new(
+        &format!("T{}:{}", tables.len(), name),
+        Neighborhood::Moore,
+        &rules,
+    )
+}
+
+// Number of possible neighborhood sums for the given number of states
+pub fn table_size(states: usize) -> usize {
+    9 * (states - 1) + 1
+}
+
+pub fn parse_totalistic(name: &str) -> Option<RuleSet> {
+    let (states, tables) = name.strip_prefix(['T', 't'])?.split_once(':')?;
+    let states = states.parse::<usize>().ok()?;
+    if !(2..=DIGITS.len()).contains(&states) {
+        return None;
+    }
+    let tables = tables
+        .split('/')
+        .map(|table| {
+            table
+                .chars()
+                .map(|c| match c.to_digit(36) {
+                    Some(cell) if (cell as usize) < states => Some(Cell(cell as u8)),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+                .filter(|table| table.len() == table_size(states))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if tables.len() != states {
+        return None;
+    }
+    Some(totalistic(&tables))
+}
@@ -2,6 +2,7 @@ use super::*;
 
 pub fn wireworld() -> RuleSet {
     RuleSet::new(
+        "wireworld",
         Neighborhood::Moore,
         &[
             Rule::Static,
@@ -0,0 +1,59 @@
+use std::fmt::Display;
+
+use crate::{
+    periodicity::{PeriodDetector, Periodicity},
+    sim::Simulation,
+};
+
+pub struct RunConfig {
+    pub generations: u64,
+    // Stop as soon as the simulation becomes static or periodic
+    pub until_stable: bool,
+    // Also stop when the state repeats at an offset
+    pub translation_invariant: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StopReason {
+    GenerationLimit,
+    Stable(Periodicity),
+}
+
+pub struct RunResult {
+    pub generations: u64,
+    pub stop_reason: StopReason,
+}
+
+impl Display for RunResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.stop_reason {
+            StopReason::GenerationLimit => write!(f, "Ran {} generations", self.generations),
+            StopReason::Stable(periodicity) => {
+                write!(f, "Ran {} generations, {}", self.generations, periodicity)
+            }
+        }
+    }
+}
+
+pub fn run(sim: &mut Simulation, config: &RunConfig) -> RunResult {
+    let start = sim.current_step();
+    let mut detector = PeriodDetector::new(config.translation_invariant);
+    let mut stop_reason = StopReason::GenerationLimit;
+    if config.until_stable {
+        detector.observe(sim);
+    }
+    while sim.current_step() - start < config.generations {
+        sim.step();
+        if !config.until_stable {
+            continue;
+        }
+        if let Some(periodicity) = detector.observe(sim) {
+            stop_reason = StopReason::Stable(periodicity);
+            break;
+        }
+    }
+    RunResult {
+        generations: sim.current_step() - start,
+        stop_reason,
+    }
+}
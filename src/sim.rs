@@ -1,11 +1,70 @@
+use std::collections::VecDeque;
+
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+
 use crate::{
+    activity::Activity,
     rules::{Cell, RuleSet},
-    space::Space,
+    space::{Space, MAX_COORD, MIN_COORD},
+    stats::GenerationStats,
 };
 
+// How to fill a region with random cells
+#[derive(Clone, Debug)]
+pub struct RandomFill {
+    // Probability of a cell being non-zero
+    pub density: f32,
+    // Relative weights of the non-zero states, uniform if empty
+    pub weights: Vec<f32>,
+}
+
+impl RandomFill {
+    // Weights of the non-zero states of a ruleset with the given number of
+    // states, a weight has to be given for each of them if any are
+    pub fn state_weights(&self, states: usize) -> Result<Vec<f32>, String> {
+        let live_states = states.saturating_sub(1);
+        if self.weights.is_empty() {
+            return Ok(vec![1.0; live_states]);
+        }
+        if self.weights.len() != live_states {
+            return Err(format!(
+                "Expected a weight for each of the {} non-zero states of the ruleset, got {}",
+                live_states,
+                self.weights.len()
+            ));
+        }
+        if self.weights.iter().any(|w| !w.is_finite() || *w < 0.0)
+            || self.weights.iter().all(|w| *w == 0.0)
+        {
+            return Err("State weights must be non-negative with at least one positive".to_owned());
+        }
+        Ok(self.weights.clone())
+    }
+}
+
+impl Default for RandomFill {
+    fn default() -> Self {
+        RandomFill {
+            density: 0.3,
+            weights: vec![],
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Simulation {
     space: Space,
     ruleset: RuleSet,
+    // Per-generation statistics of the most recent generations, only
+    // recorded when enabled
+    history: VecDeque<GenerationStats>,
+    // Number of generations kept in the history, 0 when not recording
+    history_limit: usize,
+    // Per-cell change history, only recorded when enabled
+    activity: Option<Activity>,
 }
 
 impl Simulation {
@@ -13,42 +72,205 @@ impl Simulation {
         Simulation {
             space: Space::new(),
             ruleset,
+            history: VecDeque::new(),
+            history_limit: 0,
+            activity: None,
         }
     }
 
+    // Start recording statistics from the current step on, keeping those of
+    // the last `limit` generations
+    pub fn record_statistics(&mut self, limit: usize) {
+        let stats = GenerationStats::of_current_step(&self.space, self.get_cell_types_count());
+        self.history = VecDeque::from([stats]);
+        self.history_limit = limit.max(1);
+    }
+
+    pub fn history(&self) -> &VecDeque<GenerationStats> {
+        &self.history
+    }
+
+    // Start recording per-cell activity from the current step on
+    pub fn record_activity(&mut self) {
+        self.activity = Some(Activity::new(&self.space));
+    }
+
+    pub fn stop_recording_activity(&mut self) {
+        self.activity = None;
+    }
+
+    pub fn activity(&self) -> Option<&Activity> {
+        self.activity.as_ref()
+    }
+
+    pub fn ruleset_name(&self) -> &str {
+        &self.ruleset.name
+    }
+
     pub fn get_cell_types_count(&self) -> usize {
         self.ruleset.cell_rules.len()
     }
 
+    pub fn space(&self) -> &Space {
+        &self.space
+    }
+
     pub fn current_step(&self) -> u64 {
         self.space.current_step()
     }
 
+    pub fn hash(&self) -> u64 {
+        self.space.hash()
+    }
+
+    pub fn normalized_hash(&self) -> (u64, i64, i64) {
+        self.space.normalized_hash()
+    }
+
+    pub fn bounding_box(&self) -> Option<(i64, i64, i64, i64)> {
+        self.space.bounding_box()
+    }
+
     pub fn get(&self, x: i64, y: i64) -> Cell {
         self.space.get(x, y)
     }
 
     pub fn set(&mut self, x: i64, y: i64, cell: Cell) {
         self.space.set_curr(x, y, cell);
+        if let Some(activity) = &mut self.activity {
+            activity.observe_edit(x, y, cell.0 != 0);
+        }
+    }
+
+    // Switch to another ruleset, keeping the cells. Cells in states the new
+    // ruleset doesn't have are cleared.
+    pub fn set_ruleset(&mut self, ruleset: RuleSet) {
+        self.ruleset = ruleset;
+        let states = self.get_cell_types_count();
+        for y in MIN_COORD..=MAX_COORD {
+            for x in MIN_COORD..=MAX_COORD {
+                if self.get(x, y).0 as usize >= states {
+                    self.set(x, y, Cell(0));
+                }
+            }
+        }
+        // The statistics have a column per state
+        if self.history_limit > 0 {
+            self.record_statistics(self.history_limit);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for y in MIN_COORD..=MAX_COORD {
+            for x in MIN_COORD..=MAX_COORD {
+                self.set(x, y, Cell(0));
+            }
+        }
     }
 
     pub fn step(&mut self) {
         self.ruleset.apply(&mut self.space);
+        if self.history_limit > 0 {
+            if self.history.len() == self.history_limit {
+                self.history.pop_front();
+            }
+            self.history.push_back(GenerationStats::of_next_step(
+                &self.space,
+                self.ruleset.cell_rules.len(),
+            ));
+        }
+        if let Some(activity) = &mut self.activity {
+            activity.observe_step(&self.space);
+        }
         self.space.finalize_step();
     }
 
     pub fn randomize(&mut self, x_from: i64, x_to: i64, y_from: i64, y_to: i64) {
-        // TODO: Make this parameter controllable
-        const DENSITY: f32 = 0.3;
+        self.randomize_with(
+            x_from,
+            x_to,
+            y_from,
+            y_to,
+            &RandomFill::default(),
+            &mut rand::thread_rng(),
+        )
+        .expect("Uniform weights fit any ruleset");
+    }
+
+    // Fails if the weights don't fit the ruleset, see state_weights()
+    pub fn randomize_with(
+        &mut self,
+        x_from: i64,
+        x_to: i64,
+        y_from: i64,
+        y_to: i64,
+        fill: &RandomFill,
+        rng: &mut impl Rng,
+    ) -> Result<(), String> {
+        let weights = fill.state_weights(self.get_cell_types_count())?;
+        let Ok(states) = WeightedIndex::new(weights) else {
+            // No non-zero states to fill with
+            return Ok(());
+        };
         for x in x_from..x_to {
             for y in y_from..y_to {
-                let new_cell = if rand::random::<f32>() <= DENSITY {
-                    Cell(1 + (rand::random::<u8>() % (self.get_cell_types_count() as u8 - 1)))
+                let new_cell = if rng.gen::<f32>() < fill.density {
+                    Cell(1 + states.sample(rng) as u8)
                 } else {
                     Cell(0)
                 };
-                self.space.set_curr(x, y, new_cell);
+                self.set(x, y, new_cell);
             }
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(weights: &[f32]) -> RandomFill {
+        RandomFill {
+            density: 0.5,
+            weights: weights.to_vec(),
+        }
+    }
+
+    #[test]
+    fn state_weights() {
+        assert_eq!(fill(&[]).state_weights(3), Ok(vec![1.0, 1.0]));
+        assert_eq!(fill(&[0.0, 2.0]).state_weights(3), Ok(vec![0.0, 2.0]));
+        assert!(fill(&[1.0, 2.0]).state_weights(2).is_err());
+        assert!(fill(&[1.0]).state_weights(3).is_err());
+        assert!(fill(&[0.0]).state_weights(2).is_err());
+        assert!(fill(&[-1.0, 2.0]).state_weights(3).is_err());
+        assert!(fill(&[f32::NAN]).state_weights(2).is_err());
+    }
+
+    #[test]
+    fn randomize_with_invalid_weights() {
+        let mut sim = Simulation::new(crate::rules::create_ruleset("life").unwrap());
+        let result = sim.randomize_with(0, 10, 0, 10, &fill(&[0.0, 1.0]), &mut rand::thread_rng());
+        assert!(result.is_err());
+        assert_eq!(sim.bounding_box(), None);
+        let result = sim.randomize_with(0, 10, 0, 10, &fill(&[2.0]), &mut rand::thread_rng());
+        assert!(result.is_ok());
+        assert!(sim.bounding_box().is_some());
+    }
+
+    #[test]
+    fn limited_history() {
+        let mut sim = Simulation::new(crate::rules::create_ruleset("life").unwrap());
+        for x in 0..3 {
+            sim.set(x, 0, Cell(1));
+        }
+        sim.record_statistics(4);
+        for _ in 0..10 {
+            sim.step();
+        }
+        let steps: Vec<u64> = sim.history().iter().map(|stats| stats.step).collect();
+        assert_eq!(steps, [7, 8, 9, 10]);
+        assert!(sim.history().iter().all(|stats| stats.population[1] == 3));
     }
 }
@@ -9,6 +9,9 @@ pub enum Neighborhood {
 #[derive(Clone)]
 pub struct Space {
     current_step: u64,
+    // Hash of the current cells and of the new ones computed so far
+    hash: u64,
+    new_hash: u64,
     // 4 space quadrants
     q_ne: Vec<Cell>,
     q_se: Vec<Cell>,
@@ -25,10 +28,35 @@ pub struct Space {
 const BLOCK_SIZE: usize = 128;
 const SPACE_SIZE: usize = BLOCK_SIZE * BLOCK_SIZE;
 
+// Range of coordinates mapping to distinct cells along each axis,
+// everything outside of it wraps around
+pub const MIN_COORD: i64 = -(BLOCK_SIZE as i64);
+pub const MAX_COORD: i64 = BLOCK_SIZE as i64 - 1;
+
+pub fn wrap(v: i64) -> i64 {
+    (v - MIN_COORD).rem_euclid(2 * BLOCK_SIZE as i64) + MIN_COORD
+}
+
+// Zobrist-style hash of a single cell, the hash of a space is the XOR of
+// the hashes of all its cells. Zero cells don't contribute to it.
+fn cell_hash(x: i64, y: i64, cell: Cell) -> u64 {
+    if cell.0 == 0 {
+        return 0;
+    }
+    // SplitMix64 finalizer
+    let mut z = (x as u64 & 0xffff) | (y as u64 & 0xffff) << 16 | (cell.0 as u64) << 32;
+    z = z.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
 impl Space {
     pub fn new() -> Self {
         Space {
             current_step: 0,
+            hash: 0,
+            new_hash: 0,
             q_ne: vec![Cell(0); SPACE_SIZE],
             q_se: vec![Cell(0); SPACE_SIZE],
             q_nw: vec![Cell(0); SPACE_SIZE],
@@ -44,7 +72,65 @@ impl Space {
         self.current_step
     }
 
+    // Hash of all cells, updated incrementally as cells change
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // Bounds of the non-zero cells as (x_min, x_max, y_min, y_max), inclusive.
+    // Cells on both sides of the seam make the box span the whole space.
+    pub fn bounding_box(&self) -> Option<(i64, i64, i64, i64)> {
+        let mut bounds: Option<(i64, i64, i64, i64)> = None;
+        for (quadrant, flip_x, flip_y) in [
+            (&self.q_ne, false, false),
+            (&self.q_se, false, true),
+            (&self.q_nw, true, false),
+            (&self.q_sw, true, true),
+        ] {
+            for (idx, cell) in quadrant.iter().enumerate() {
+                if cell.0 == 0 {
+                    continue;
+                }
+                let (x, y) = ((idx % BLOCK_SIZE) as i64, (idx / BLOCK_SIZE) as i64);
+                let x = if flip_x { -x - 1 } else { x };
+                let y = if flip_y { -y - 1 } else { y };
+                bounds = Some(match bounds {
+                    None => (x, x, y, y),
+                    Some((x_min, x_max, y_min, y_max)) => {
+                        (x_min.min(x), x_max.max(x), y_min.min(y), y_max.max(y))
+                    }
+                });
+            }
+        }
+        bounds
+    }
+
+    // Hash of the non-zero cells relative to their bounding box, equal for
+    // translated copies of the same contents. Returns the hash along with
+    // the top left corner of the bounding box.
+    pub fn normalized_hash(&self) -> (u64, i64, i64) {
+        let Some((x_min, x_max, y_min, y_max)) = self.bounding_box() else {
+            return (0, MAX_COORD, MAX_COORD);
+        };
+        let mut hash = 0;
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                hash ^= cell_hash(x - x_min, y - y_min, self.get(x, y));
+            }
+        }
+        (hash, x_min, y_min)
+    }
+
+    // Reset the new values to zero, for rules that only update part of the space
+    pub fn clear_new(&mut self) {
+        self.new_q_ne.fill(Cell(0));
+        self.new_q_se.fill(Cell(0));
+        self.new_q_nw.fill(Cell(0));
+        self.new_q_sw.fill(Cell(0));
+    }
+
     pub fn finalize_step(&mut self) {
+        self.hash = self.new_hash;
         std::mem::swap(&mut self.q_ne, &mut self.new_q_ne);
         std::mem::swap(&mut self.q_se, &mut self.new_q_se);
         std::mem::swap(&mut self.q_nw, &mut self.new_q_nw);
@@ -54,57 +140,83 @@ impl Space {
 
     #[allow(clippy::collapsible_else_if)]
     pub fn get(&self, x: i64, y: i64) -> Cell {
-        let x = x % BLOCK_SIZE as i64;
-        let y = y % BLOCK_SIZE as i64;
+        let x = wrap(x);
+        let y = wrap(y);
         if x >= 0 {
             if y >= 0 {
                 self.q_ne[x as usize + y as usize * BLOCK_SIZE]
             } else {
-                self.q_se[x as usize + (-y) as usize * BLOCK_SIZE]
+                self.q_se[x as usize + (-y - 1) as usize * BLOCK_SIZE]
             }
         } else {
             if y >= 0 {
-                self.q_nw[(-x) as usize + y as usize * BLOCK_SIZE]
+                self.q_nw[(-x - 1) as usize + y as usize * BLOCK_SIZE]
             } else {
-                self.q_sw[(-x) as usize + (-y) as usize * BLOCK_SIZE]
+                self.q_sw[(-x - 1) as usize + (-y - 1) as usize * BLOCK_SIZE]
+            }
+        }
+    }
+
+    // Value computed for the next step, only valid before finalize_step()
+    #[allow(clippy::collapsible_else_if)]
+    pub fn get_new(&self, x: i64, y: i64) -> Cell {
+        let x = wrap(x);
+        let y = wrap(y);
+        if x >= 0 {
+            if y >= 0 {
+                self.new_q_ne[x as usize + y as usize * BLOCK_SIZE]
+            } else {
+                self.new_q_se[x as usize + (-y - 1) as usize * BLOCK_SIZE]
+            }
+        } else {
+            if y >= 0 {
+                self.new_q_nw[(-x - 1) as usize + y as usize * BLOCK_SIZE]
+            } else {
+                self.new_q_sw[(-x - 1) as usize + (-y - 1) as usize * BLOCK_SIZE]
             }
         }
     }
 
     #[allow(clippy::collapsible_else_if)]
     pub fn set_new(&mut self, x: i64, y: i64, cell: Cell) {
-        let x = x % BLOCK_SIZE as i64;
-        let y = y % BLOCK_SIZE as i64;
+        let x = wrap(x);
+        let y = wrap(y);
+        let old_cell = self.get(x, y);
+        if old_cell != cell {
+            self.new_hash ^= cell_hash(x, y, old_cell) ^ cell_hash(x, y, cell);
+        }
         if x >= 0 {
             if y >= 0 {
                 self.new_q_ne[x as usize + y as usize * BLOCK_SIZE] = cell;
             } else {
-                self.new_q_se[x as usize + (-y) as usize * BLOCK_SIZE] = cell;
+                self.new_q_se[x as usize + (-y - 1) as usize * BLOCK_SIZE] = cell;
             }
         } else {
             if y >= 0 {
-                self.new_q_nw[(-x) as usize + y as usize * BLOCK_SIZE] = cell;
+                self.new_q_nw[(-x - 1) as usize + y as usize * BLOCK_SIZE] = cell;
             } else {
-                self.new_q_sw[(-x) as usize + (-y) as usize * BLOCK_SIZE] = cell;
+                self.new_q_sw[(-x - 1) as usize + (-y - 1) as usize * BLOCK_SIZE] = cell;
             }
         }
     }
 
     #[allow(clippy::collapsible_else_if)]
     pub fn set_curr(&mut self, x: i64, y: i64, cell: Cell) {
-        let x = x % BLOCK_SIZE as i64;
-        let y = y % BLOCK_SIZE as i64;
+        let x = wrap(x);
+        let y = wrap(y);
+        self.hash ^= cell_hash(x, y, self.get(x, y)) ^ cell_hash(x, y, cell);
+        self.new_hash = self.hash;
         if x >= 0 {
             if y >= 0 {
                 self.q_ne[x as usize + y as usize * BLOCK_SIZE] = cell;
             } else {
-                self.q_se[x as usize + (-y) as usize * BLOCK_SIZE] = cell;
+                self.q_se[x as usize + (-y - 1) as usize * BLOCK_SIZE] = cell;
             }
         } else {
             if y >= 0 {
-                self.q_nw[(-x) as usize + y as usize * BLOCK_SIZE] = cell;
+                self.q_nw[(-x - 1) as usize + y as usize * BLOCK_SIZE] = cell;
             } else {
-                self.q_sw[(-x) as usize + (-y) as usize * BLOCK_SIZE] = cell;
+                self.q_sw[(-x - 1) as usize + (-y - 1) as usize * BLOCK_SIZE] = cell;
             }
         }
     }
@@ -151,3 +263,64 @@ impl Space {
         f(x - 1, y);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_coordinates() {
+        assert_eq!(wrap(MAX_COORD + 1), MIN_COORD);
+        assert_eq!(wrap(MIN_COORD - 1), MAX_COORD);
+        assert_eq!(wrap(3 * (MAX_COORD + 1) + 5), MIN_COORD + 5);
+
+        let mut space = Space::new();
+        space.set_curr(MAX_COORD + 1, MIN_COORD - 1, Cell(1));
+        assert_eq!(space.get(MIN_COORD, MAX_COORD), Cell(1));
+    }
+
+    #[test]
+    fn wraparound_neighbors() {
+        let mut space = Space::new();
+        // Opposite corners are diagonal neighbors
+        space.set_curr(MIN_COORD, MIN_COORD, Cell(1));
+        space.set_curr(MAX_COORD, MAX_COORD, Cell(1));
+        assert_eq!(
+            space.count(MAX_COORD, MAX_COORD, Cell(1), Neighborhood::Moore),
+            2
+        );
+        assert_eq!(
+            space.count(MIN_COORD, MIN_COORD, Cell(1), Neighborhood::Moore),
+            2
+        );
+        assert_eq!(
+            space.count(MAX_COORD, MAX_COORD, Cell(1), Neighborhood::VonNeumann),
+            0
+        );
+
+        // Across a single edge
+        space.set_curr(MIN_COORD, 0, Cell(1));
+        assert_eq!(
+            space.count(MAX_COORD, 0, Cell(1), Neighborhood::VonNeumann),
+            1
+        );
+        assert_eq!(space.count(MAX_COORD, 1, Cell(1), Neighborhood::Moore), 1);
+        assert_eq!(space.count(MAX_COORD, 2, Cell(1), Neighborhood::Moore), 0);
+    }
+
+    #[test]
+    fn blinker_across_edge() {
+        let ruleset = crate::rules::create_ruleset("life").unwrap();
+        let mut space = Space::new();
+        for x in [MAX_COORD, MIN_COORD, MIN_COORD + 1] {
+            space.set_curr(x, MIN_COORD, Cell(1));
+        }
+        ruleset.apply(&mut space);
+        space.finalize_step();
+        for y in [MAX_COORD, MIN_COORD, MIN_COORD + 1] {
+            assert_eq!(space.get(MIN_COORD, y), Cell(1));
+        }
+        assert_eq!(space.get(MAX_COORD, MIN_COORD), Cell(0));
+        assert_eq!(space.get(MIN_COORD + 1, MIN_COORD), Cell(0));
+    }
+}
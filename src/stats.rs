@@ -0,0 +1,97 @@
+use crate::{
+    rules::Cell,
+    space::{Space, MAX_COORD, MIN_COORD},
+};
+
+#[derive(Clone, Debug)]
+pub struct GenerationStats {
+    pub step: u64,
+    // Number of cells in each state
+    pub population: Vec<usize>,
+    // Cells that turned from zero to non-zero
+    pub births: usize,
+    // Cells that turned from non-zero to zero
+    pub deaths: usize,
+    // Size of the bounding box of non-zero cells
+    pub width: i64,
+    pub height: i64,
+}
+
+impl GenerationStats {
+    fn collect(
+        step: u64,
+        cell_types_count: usize,
+        mut cell: impl FnMut(i64, i64) -> (Cell, Cell),
+    ) -> Self {
+        let mut stats = GenerationStats {
+            step,
+            population: vec![0; cell_types_count],
+            births: 0,
+            deaths: 0,
+            width: 0,
+            height: 0,
+        };
+        let (mut x_min, mut x_max, mut y_min, mut y_max) =
+            (MAX_COORD, MIN_COORD, MAX_COORD, MIN_COORD);
+        for y in MIN_COORD..=MAX_COORD {
+            for x in MIN_COORD..=MAX_COORD {
+                let (old_cell, new_cell) = cell(x, y);
+                stats.population[new_cell.0 as usize] += 1;
+                match (old_cell.0, new_cell.0) {
+                    (0, 0) => continue,
+                    (0, _) => stats.births += 1,
+                    (_, 0) => stats.deaths += 1,
+                    _ => {}
+                }
+                if new_cell.0 != 0 {
+                    x_min = x_min.min(x);
+                    x_max = x_max.max(x);
+                    y_min = y_min.min(y);
+                    y_max = y_max.max(y);
+                }
+            }
+        }
+        if x_min <= x_max {
+            stats.width = x_max - x_min + 1;
+            stats.height = y_max - y_min + 1;
+        }
+        stats
+    }
+
+    pub fn of_current_step(space: &Space, cell_types_count: usize) -> Self {
+        GenerationStats::collect(space.current_step(), cell_types_count, |x, y| {
+            let cell = space.get(x, y);
+            (cell, cell)
+        })
+    }
+
+    // Statistics of the step being computed, only valid before Space::finalize_step()
+    pub fn of_next_step(space: &Space, cell_types_count: usize) -> Self {
+        GenerationStats::collect(space.current_step() + 1, cell_types_count, |x, y| {
+            (space.get(x, y), space.get_new(x, y))
+        })
+    }
+}
+
+pub fn to_csv<'a>(history: impl IntoIterator<Item = &'a GenerationStats>) -> String {
+    let mut history = history.into_iter().peekable();
+    let Some(first) = history.peek() else {
+        return String::new();
+    };
+    let mut result = "step".to_owned();
+    for state in 0..first.population.len() {
+        result += &format!(",population_{}", state);
+    }
+    result += ",births,deaths,width,height\n";
+    for stats in history {
+        result += &stats.step.to_string();
+        for count in stats.population.iter() {
+            result += &format!(",{}", count);
+        }
+        result += &format!(
+            ",{},{},{},{}\n",
+            stats.births, stats.deaths, stats.width, stats.height
+        );
+    }
+    result
+}